@@ -0,0 +1,101 @@
+//! Builds each broad phase over randomly placed particles of about the same size
+//! and finds every overlapping pair, like `resolve_collisions` does every step.
+
+use std::time::{Duration, Instant};
+
+use glam::Vec2;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use particle_simulator::broad_phase::{BroadPhase, SpatialGrid};
+use particle_simulator::quadtree::{QuadTree, Rect};
+
+const COUNTS: [usize; 3] = [10_000, 100_000, 1_000_000];
+
+// each particle gets about this much room, a moderately dense gas
+const AREA_PER_PARTICLE: f32 = 200.0;
+
+const RADIUS: (f32, f32) = (2.0, 3.0);
+
+fn particles(count: usize) -> (f32, Vec<(Vec2, f32)>) {
+    let mut rng = ChaCha8Rng::seed_from_u64(count as u64);
+    let size = (count as f32 * AREA_PER_PARTICLE).sqrt();
+
+    let particles = (0..count)
+        .map(|_| {
+            let pos = glam::vec2(rng.random_range(0.0..size), rng.random_range(0.0..size));
+            (pos, rng.random_range(RADIUS.0..RADIUS.1))
+        })
+        .collect();
+
+    (size, particles)
+}
+
+/// Time to insert everything and the time to query every particle, with the number
+/// of pairs found so both can be checked against each other
+fn run(
+    mut broad_phase: impl BroadPhase<usize>,
+    particles: &[(Vec2, f32)],
+) -> (Duration, Duration, usize) {
+    let start = Instant::now();
+
+    for (index, (pos, radius)) in particles.iter().enumerate() {
+        broad_phase.insert(*pos, *radius, index);
+    }
+
+    let built = start.elapsed();
+    let start = Instant::now();
+
+    let pairs = particles
+        .iter()
+        .enumerate()
+        .map(|(a, (pos, radius))| {
+            broad_phase
+                .query(*pos, *radius)
+                .into_iter()
+                .filter(|b| *b > a)
+                .count()
+        })
+        .sum();
+
+    (built, start.elapsed(), pairs)
+}
+
+fn main() {
+    println!(
+        "{:>10} {:>10} {:>12} {:>12} {:>12} {:>10}",
+        "particles", "structure", "build", "query", "total", "pairs"
+    );
+
+    for count in COUNTS {
+        let (size, particles) = particles(count);
+
+        let quadtree = QuadTree::new(
+            32,
+            Rect {
+                left: 0.0,
+                top: 0.0,
+                width: size,
+                height: size,
+            },
+        );
+        let grid = SpatialGrid::new(2.0 * RADIUS.1, count);
+
+        let results = [
+            ("quadtree", run(quadtree, &particles)),
+            ("grid", run(grid, &particles)),
+        ];
+
+        for (name, (build, query, pairs)) in results {
+            println!(
+                "{count:>10} {name:>10} {build:>12.2?} {query:>12.2?} {:>12.2?} {pairs:>10}",
+                build + query
+            );
+        }
+
+        assert_eq!(
+            results[0].1 .2, results[1].1 .2,
+            "both must find the same pairs"
+        );
+    }
+}
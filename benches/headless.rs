@@ -0,0 +1,111 @@
+//! Steps a large scene without a window on one thread and then on every thread
+//! rayon uses by default, which `RAYON_NUM_THREADS` changes, to show how much
+//! parallel tree building and collision resolution save.
+
+use std::time::{Duration, Instant};
+
+use particle_simulator::Scene;
+use particle_simulator::FLOATS_PER_INSTANCE;
+
+const STEPS: usize = 20;
+
+// a tightly packed pile under gravity, so every particle is in several contacts,
+// and a collapsing cloud pulled together by Barnes-Hut gravity
+const SCENES: [(&str, &str); 2] = [
+    (
+        "pile",
+        r#"
+        size = [3000, 3000]
+        seed = 1
+        contact_passes = 4
+
+        [[force_fields]]
+        gravity = [0, 500]
+
+        [[lattices]]
+        origin = [100, 100]
+        columns = 250
+        rows = 200
+        spacing = [11, 11]
+        velocity_jitter = 20
+        radius = 5
+        "#,
+    ),
+    (
+        "cloud",
+        r#"
+        size = [3000, 3000]
+        seed = 2
+
+        [boundary]
+        all = "open"
+
+        [gravitation]
+        constant = 20
+        theta = 0.5
+        softening = 5
+
+        [[lattices]]
+        origin = [250, 250]
+        columns = 250
+        rows = 200
+        spacing = [10, 10]
+        velocity_jitter = 5
+        radius = 2
+        mass = 10
+        "#,
+    ),
+];
+
+/// Time per step and where the particles ended up. Simulations can't move between
+/// threads, so it's made inside the pool.
+fn run(source: &str, threads: Option<usize>) -> (Duration, Vec<f32>) {
+    let mut builder = rayon::ThreadPoolBuilder::new();
+
+    if let Some(threads) = threads {
+        builder = builder.num_threads(threads);
+    }
+
+    builder.build().unwrap().install(|| {
+        let mut simulation = Scene::parse(source).unwrap().into_simulation();
+
+        let start = Instant::now();
+        for _ in 0..STEPS {
+            simulation.step(1.0 / 240.0);
+        }
+
+        let elapsed = start.elapsed() / STEPS as u32;
+        let instances = simulation.instances().as_slice().to_vec();
+        (elapsed, instances)
+    })
+}
+
+fn main() {
+    let threads = rayon::current_num_threads();
+
+    println!(
+        "{:>8} {:>10} {:>14} {:>14} {:>8}",
+        "scene",
+        "particles",
+        "1 thread",
+        format!("{threads} threads"),
+        "speedup"
+    );
+
+    for (name, source) in SCENES {
+        let (serial, expected) = run(source, Some(1));
+        let (parallel, instances) = run(source, None);
+
+        println!(
+            "{name:>8} {:>10} {serial:>14.2?} {parallel:>14.2?} {:>7.2}x",
+            instances.len() / FLOATS_PER_INSTANCE,
+            serial.as_secs_f64() / parallel.as_secs_f64()
+        );
+
+        // the work is split so the result doesn't depend on the number of threads
+        assert_eq!(
+            expected, instances,
+            "every thread count must give the same result"
+        );
+    }
+}
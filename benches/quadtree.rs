@@ -0,0 +1,176 @@
+//! Building and querying a `QuadTree` the way the collision and gravity systems
+//! use it, over particles of about the same size spread out like a dense gas.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use glam::Vec2;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use particle_simulator::quadtree::{QuadTree, Rect};
+
+const COUNTS: [usize; 2] = [10_000, 100_000];
+
+const CAPACITY: usize = 32;
+
+// each particle gets about this much room
+const AREA_PER_PARTICLE: f32 = 200.0;
+
+fn particles(count: usize) -> (Rect, Vec<(Vec2, f32, usize)>) {
+    let mut rng = ChaCha8Rng::seed_from_u64(count as u64);
+    let size = (count as f32 * AREA_PER_PARTICLE).sqrt();
+
+    let particles = (0..count)
+        .map(|index| {
+            let pos = glam::vec2(rng.random_range(0.0..size), rng.random_range(0.0..size));
+            (pos, rng.random_range(2.0..3.0), index)
+        })
+        .collect();
+
+    let boundary = Rect {
+        left: 0.0,
+        top: 0.0,
+        width: size,
+        height: size,
+    };
+
+    (boundary, particles)
+}
+
+fn build(boundary: &Rect, particles: &[(Vec2, f32, usize)]) -> QuadTree<usize> {
+    let mut tree = QuadTree::new(CAPACITY, boundary.clone());
+
+    for particle in particles {
+        tree.push(*particle);
+    }
+
+    tree
+}
+
+fn bench_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+
+    for count in COUNTS {
+        let (boundary, particles) = particles(count);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            &particles,
+            |b, particles| b.iter(|| build(&boundary, black_box(particles))),
+        );
+    }
+
+    group.finish();
+}
+
+/// Like `build` but refilling one tree, as the systems do every step
+fn bench_rebuild(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rebuild");
+
+    for count in COUNTS {
+        let (boundary, particles) = particles(count);
+        let mut tree = build(&boundary, &particles);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            &particles,
+            |b, particles| {
+                b.iter(|| {
+                    tree.clear(boundary.clone());
+
+                    for particle in black_box(particles) {
+                        tree.push(*particle);
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query");
+
+    for count in COUNTS {
+        let (boundary, particles) = particles(count);
+        let tree = build(&boundary, &particles);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            &particles,
+            |b, particles| {
+                b.iter(|| {
+                    particles
+                        .iter()
+                        .map(|(pos, radius, _)| tree.query(*pos, *radius).len())
+                        .sum::<usize>()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_k_nearest(c: &mut Criterion) {
+    let mut group = c.benchmark_group("k_nearest");
+
+    for count in COUNTS {
+        let (boundary, particles) = particles(count);
+        let tree = build(&boundary, &particles);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            &particles,
+            |b, particles| {
+                b.iter(|| {
+                    particles
+                        .iter()
+                        .step_by(10)
+                        .map(|(pos, ..)| tree.k_nearest(*pos, 8).len())
+                        .sum::<usize>()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_barnes_hut(c: &mut Criterion) {
+    let mut group = c.benchmark_group("barnes_hut");
+
+    for count in COUNTS {
+        let (boundary, particles) = particles(count);
+        let mut tree = build(&boundary, &particles);
+        tree.compute_mass_distribution(&|_| 1.0);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            &particles,
+            |b, particles| {
+                b.iter(|| {
+                    particles
+                        .iter()
+                        .step_by(10)
+                        .map(|(pos, ..)| tree.barnes_hut_acceleration(*pos, 0.5, 1.0, &|_| 1.0))
+                        .sum::<Vec2>()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_build,
+    bench_rebuild,
+    bench_query,
+    bench_k_nearest,
+    bench_barnes_hut
+);
+criterion_main!(benches);
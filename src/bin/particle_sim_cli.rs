@@ -0,0 +1,154 @@
+use std::io::Write;
+
+use legion::*;
+
+use particle_simulator::broad_phase::BroadPhaseKind;
+use particle_simulator::components::*;
+use particle_simulator::{Integrator, Scene, Simulation, Snapshot};
+
+const USAGE: &str = "usage: particle_sim_cli <scene> [--steps N] [--dt SECONDS] [--integrator NAME] [--broad-phase NAME] [--seed N] [--output FILE] [--save SNAPSHOT]
+
+<scene> can also be a snapshot file ending in `.bin` or `.ron`";
+
+struct Args {
+    scene: String,
+    steps: usize,
+    dt: f32,
+    integrator: Option<Integrator>,
+    broad_phase: Option<BroadPhaseKind>,
+    seed: Option<u64>,
+    output: String,
+    save: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args().skip(1);
+
+    let mut scene = None;
+    let mut steps = 1000;
+    let mut dt: f32 = 1.0 / 60.0;
+    let mut integrator = None;
+    let mut broad_phase = None;
+    let mut seed = None;
+    let mut output = "trajectory.csv".to_string();
+    let mut save = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for `{arg}`"));
+
+        match arg.as_str() {
+            "--steps" => {
+                steps = value()?
+                    .parse()
+                    .map_err(|_| "`--steps` must be a whole number")?
+            }
+
+            "--dt" => dt = value()?.parse().map_err(|_| "`--dt` must be a number")?,
+            "--integrator" => integrator = Some(value()?.parse()?),
+            "--broad-phase" => broad_phase = Some(value()?.parse()?),
+            "--seed" => {
+                seed = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "`--seed` must be a whole number")?,
+                )
+            }
+
+            "--output" => output = value()?,
+            "--save" => save = Some(value()?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{arg}`")),
+            _ => scene = Some(arg),
+        }
+    }
+
+    if !(dt.is_finite() && dt > 0.0) {
+        return Err("`--dt` must be positive".to_string());
+    }
+
+    Ok(Args {
+        scene: scene.ok_or("missing scene file")?,
+        steps,
+        dt,
+        integrator,
+        broad_phase,
+        seed,
+        output,
+        save,
+    })
+}
+
+/// Writes one csv row per particle
+fn write_state(out: &mut impl Write, step: usize, simulation: &Simulation) -> std::io::Result<()> {
+    let instances = simulation.instances();
+
+    let mut rows = <(&EntityIndex, &Velocity)>::query()
+        .iter(&simulation.world)
+        .map(|(EntityIndex(index), Velocity(vel))| (*index, *vel))
+        .collect::<Vec<_>>();
+
+    rows.sort_unstable_by_key(|(index, _)| *index);
+
+    for (index, vel) in rows {
+        let [x, y, radius, ..] = instances.get(index);
+        writeln!(out, "{step},{index},{x},{y},{radius},{},{}", vel.x, vel.y)?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("error: {e}\n{USAGE}");
+        std::process::exit(2);
+    });
+
+    if let Err(e) = run(&args) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let mut simulation = if args.scene.ends_with(".bin") || args.scene.ends_with(".ron") {
+        Snapshot::load(&args.scene)?.restore()
+    } else {
+        let mut scene = Scene::from_file(&args.scene)?;
+        scene.seed = args.seed.or(scene.seed);
+        scene.into_simulation()
+    };
+
+    println!("seed: {}", simulation.seed());
+
+    if let Some(integrator) = args.integrator {
+        simulation.resources.insert(integrator);
+    }
+
+    if let Some(broad_phase) = args.broad_phase {
+        simulation.resources.insert(broad_phase);
+    }
+
+    let mut out = std::io::BufWriter::new(std::fs::File::create(&args.output)?);
+
+    writeln!(out, "step,index,x,y,radius,vel_x,vel_y")?;
+    write_state(&mut out, 0, &simulation)?;
+
+    for step in 1..=args.steps {
+        simulation.step(args.dt);
+        write_state(&mut out, step, &simulation)?;
+    }
+
+    out.flush()?;
+
+    if let Some(path) = &args.save {
+        Snapshot::capture(&simulation).save(path)?;
+    }
+
+    println!(
+        "{} steps of {} particles written to {}",
+        args.steps,
+        simulation.len(),
+        args.output
+    );
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// What happens to a particle reaching one edge of the domain
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Boundary {
+    /// Bounces back keeping `restitution` of the normal velocity
+    Reflect { restitution: f32 },
+
+    /// Comes back in through the opposite edge, both edges of an axis should wrap.
+    /// Only the centre wraps, circles near the edge don't collide with the ones
+    /// on the other side of it.
+    Wrap,
+
+    /// Despawned once its centre crosses the edge
+    Absorb,
+
+    /// Keeps going, the domain is unbounded on this side
+    Open,
+}
+
+impl Default for Boundary {
+    fn default() -> Self {
+        Self::Reflect { restitution: 1.0 }
+    }
+}
+
+impl std::str::FromStr for Boundary {
+    type Err = String;
+
+    /// Reflecting edges parsed this way are perfectly elastic
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reflect" => Ok(Self::default()),
+            "wrap" => Ok(Self::Wrap),
+            "absorb" => Ok(Self::Absorb),
+            "open" => Ok(Self::Open),
+            _ => Err(format!(
+                "unknown boundary `{s}`, expected one of reflect, wrap, absorb, open"
+            )),
+        }
+    }
+}
+
+/// Gap to an edge, as a fraction of the radius, below which a circle counts as resting
+/// against it. Bouncing particles lift off a little every step while at rest.
+const CONTACT_SLOP: f32 = 0.1;
+
+/// The simulated area, from the origin to `size`, independent of any window
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Domain {
+    pub size: Vec2,
+    pub left: Boundary,
+    pub right: Boundary,
+    pub top: Boundary,
+    pub bottom: Boundary,
+}
+
+impl Domain {
+    /// Reflecting on every edge
+    pub fn new(size: Vec2) -> Self {
+        Self::with_boundary(size, Boundary::default())
+    }
+
+    pub fn with_boundary(size: Vec2, boundary: Boundary) -> Self {
+        Self {
+            size,
+            left: boundary,
+            right: boundary,
+            top: boundary,
+            bottom: boundary,
+        }
+    }
+
+    /// Applies the edges to a particle with `surface`, returns false if it has to be despawned
+    pub fn constrain(&self, pos: &mut Vec2, vel: &mut Vec2, radius: f32, surface: Surface) -> bool {
+        // comparisons with NaN are false, so broken positions would slip through
+        if !pos.is_finite() {
+            return false;
+        }
+
+        [
+            (Vec2::X, self.left, self.right),
+            (Vec2::Y, self.top, self.bottom),
+        ]
+        .into_iter()
+        .all(|(axis, low, high)| {
+            constrain_axis(pos, vel, radius, surface, axis, self.size, low, high)
+        })
+    }
+
+    /// Whether a reflecting edge the circle rests against keeps it from moving along
+    /// `direction`
+    pub fn blocks(&self, pos: Vec2, radius: f32, direction: Vec2) -> bool {
+        let resting = |boundary, gap: f32| {
+            matches!(boundary, Boundary::Reflect { .. }) && gap <= radius * CONTACT_SLOP
+        };
+
+        (direction.x < 0.0 && resting(self.left, pos.x - radius))
+            || (direction.x > 0.0 && resting(self.right, self.size.x - pos.x - radius))
+            || (direction.y < 0.0 && resting(self.top, pos.y - radius))
+            || (direction.y > 0.0 && resting(self.bottom, self.size.y - pos.y - radius))
+    }
+}
+
+/// One axis of `Domain::constrain`, `low` is the edge at 0 and `high` the one at the
+/// domain's `size`
+#[allow(clippy::too_many_arguments)]
+fn constrain_axis(
+    pos: &mut Vec2,
+    vel: &mut Vec2,
+    radius: f32,
+    surface: Surface,
+    axis: Vec2,
+    size: Vec2,
+    low: Boundary,
+    high: Boundary,
+) -> bool {
+    let extent = size.dot(axis);
+    let along = pos.dot(axis);
+
+    // (boundary, normal pointing back inside, where the centre stops when reflecting,
+    // whether the centre crossed the edge)
+    let (boundary, normal, clamped, outside) = if along - radius < 0.0 {
+        (low, axis, radius, along < 0.0)
+    } else if along + radius >= extent {
+        (high, -axis, extent - radius, along >= extent)
+    } else {
+        return true;
+    };
+
+    match boundary {
+        Boundary::Reflect { restitution } => {
+            *pos += axis * (clamped - along);
+
+            let wall = Surface {
+                restitution,
+                friction: 0.0,
+            };
+
+            colliders::bounce(vel, normal, wall.combine(surface));
+        }
+
+        // wrapping and absorbing act on the centre so particles slide over the edge
+        Boundary::Wrap if outside => *pos += axis * (along.rem_euclid(extent) - along),
+        Boundary::Absorb if outside => return false,
+        _ => {}
+    }
+
+    true
+}
@@ -0,0 +1,207 @@
+use glam::{I64Vec2, IVec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// Finds the circles that may touch a given one without testing every pair,
+/// implemented by `QuadTree` and `SpatialGrid`
+pub trait BroadPhase<T> {
+    /// Adds a circle carrying `data`
+    fn insert(&mut self, centre: Vec2, radius: f32, data: T);
+
+    /// Get all the data whose circle overlaps the specified one
+    fn query(&self, centre: Vec2, radius: f32) -> Vec<T>;
+}
+
+impl<T: Clone> BroadPhase<T> for QuadTree<T> {
+    fn insert(&mut self, centre: Vec2, radius: f32, data: T) {
+        self.push((centre, radius, data));
+    }
+
+    fn query(&self, centre: Vec2, radius: f32) -> Vec<T> {
+        QuadTree::query(self, centre, radius)
+    }
+}
+
+/// Which `BroadPhase` collisions are found with, stored as a resource
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BroadPhaseKind {
+    /// Adapts to any mix of sizes and clustering
+    #[default]
+    QuadTree,
+
+    /// Faster when the radii are about the same, a single large particle
+    /// makes every cell as big as it is
+    Grid,
+}
+
+impl std::str::FromStr for BroadPhaseKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "quadtree" => Ok(Self::QuadTree),
+            "grid" => Ok(Self::Grid),
+            _ => Err(format!(
+                "unknown broad phase `{s}`, expected one of quadtree, grid"
+            )),
+        }
+    }
+}
+
+const NONE: usize = usize::MAX;
+
+/// Circles bucketed by the square cell their centre is in, cells are hashed
+/// so the grid is unbounded and its memory only grows with the number of circles.
+///
+/// Cells should be about the diameter of the circles, a query visits every cell
+/// within its radius plus the largest radius inserted.
+#[derive(Debug, Clone)]
+pub struct SpatialGrid<T> {
+    cell_size: f32,
+
+    /// First entry of every bucket, chained through `next`
+    heads: Vec<usize>,
+    next: Vec<usize>,
+
+    /// (Cell, Position, Radius, Data)
+    entries: Vec<(IVec2, Vec2, f32, T)>,
+
+    max_radius: f32,
+}
+
+impl<T: Clone> SpatialGrid<T> {
+    /// `expected` circles fit before the buckets have to grow
+    pub fn new(cell_size: f32, expected: usize) -> Self {
+        debug_assert!(cell_size > 0.0, "cells must have a size");
+
+        Self {
+            cell_size,
+            heads: vec![NONE; expected.max(1).next_power_of_two()],
+            next: Vec::with_capacity(expected),
+            entries: Vec::with_capacity(expected),
+            max_radius: 0.0,
+        }
+    }
+
+    /// Removes every circle and sets a new cell size, keeping the memory so the
+    /// grid can be refilled without allocating
+    pub fn clear(&mut self, cell_size: f32) {
+        debug_assert!(cell_size > 0.0, "cells must have a size");
+
+        self.cell_size = cell_size;
+        self.heads.fill(NONE);
+        self.next.clear();
+        self.entries.clear();
+        self.max_radius = 0.0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn push(&mut self, centre: Vec2, radius: f32, data: T) {
+        // keep chains short, about one cell per bucket
+        if self.entries.len() >= self.heads.len() {
+            self.rehash(self.heads.len() * 2);
+        }
+
+        let cell = self.cell(centre);
+        let bucket = self.bucket(cell);
+
+        self.next.push(self.heads[bucket]);
+        self.heads[bucket] = self.entries.len();
+        self.entries.push((cell, centre, radius, data));
+        self.max_radius = self.max_radius.max(radius);
+    }
+
+    /// Get all the points whose circle overlaps the specified one
+    pub fn query(&self, circle_centre: Vec2, circle_radius: f32) -> Vec<T> {
+        let overlaps = |(_, centre, radius, _): &&(IVec2, Vec2, f32, T)| {
+            cc_intersection(*centre, *radius, circle_centre, circle_radius)
+        };
+
+        let reach = Vec2::splat(circle_radius + self.max_radius);
+        let min = self.cell(circle_centre - reach);
+        let max = self.cell(circle_centre + reach);
+
+        // cells saturate for huge circles, counting them overflows an i32
+        let cells = max.as_i64vec2() - min.as_i64vec2() + I64Vec2::ONE;
+
+        // a huge circle is cheaper to check against everything
+        if cells.x.saturating_mul(cells.y) >= self.heads.len() as i64 {
+            return self
+                .entries
+                .iter()
+                .filter(overlaps)
+                .map(|(.., data)| data.clone())
+                .collect();
+        }
+
+        let mut found = vec![];
+
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = IVec2::new(x, y);
+                let mut entry = self.heads[self.bucket(cell)];
+
+                while entry != NONE {
+                    let item = &self.entries[entry];
+
+                    // other cells can share the bucket
+                    if item.0 == cell && overlaps(&item) {
+                        found.push(item.3.clone());
+                    }
+
+                    entry = self.next[entry];
+                }
+            }
+        }
+
+        found
+    }
+
+    fn cell(&self, pos: Vec2) -> IVec2 {
+        (pos / self.cell_size).floor().as_ivec2()
+    }
+
+    fn bucket(&self, cell: IVec2) -> usize {
+        bucket(cell, self.heads.len())
+    }
+
+    fn rehash(&mut self, buckets: usize) {
+        self.heads = vec![NONE; buckets];
+
+        for (entry, (cell, ..)) in self.entries.iter().enumerate() {
+            let bucket = bucket(*cell, buckets);
+            self.next[entry] = self.heads[bucket];
+            self.heads[bucket] = entry;
+        }
+    }
+}
+
+/// Index of the bucket holding `cell`, `buckets` is a power of two
+fn bucket(cell: IVec2, buckets: usize) -> usize {
+    let hash =
+        (cell.x as u32).wrapping_mul(0x9e37_79b1) ^ (cell.y as u32).wrapping_mul(0x85eb_ca77);
+
+    (hash.rotate_left(15) as usize) & (buckets - 1)
+}
+
+impl<T: Clone> BroadPhase<T> for SpatialGrid<T> {
+    fn insert(&mut self, centre: Vec2, radius: f32, data: T) {
+        self.push(centre, radius, data);
+    }
+
+    fn query(&self, centre: Vec2, radius: f32) -> Vec<T> {
+        SpatialGrid::query(self, centre, radius)
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// Static geometry particles bounce off, in world coordinates
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ColliderShape {
+    /// Two sided, from `a` to `b`
+    Segment {
+        a: Vec2,
+        b: Vec2,
+    },
+
+    Aabb {
+        min: Vec2,
+        max: Vec2,
+    },
+
+    Circle {
+        centre: Vec2,
+        radius: f32,
+    },
+
+    /// Convex, in either winding order
+    Polygon(Vec<Vec2>),
+}
+
+/// How far to push a particle and along which direction to get it out of a collider
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    /// Unit length, pointing away from the collider
+    pub normal: Vec2,
+    pub depth: f32,
+}
+
+impl ColliderShape {
+    /// Contact with the circle at `centre`, `None` if they don't overlap
+    pub fn contact(&self, centre: Vec2, radius: f32) -> Option<Contact> {
+        match self {
+            Self::Segment { a, b } => {
+                let closest = closest_on_segment(centre, *a, *b);
+
+                // a centre right on the segment gets pushed out along its perpendicular
+                let fallback = (*b - *a).perp().normalize_or(Vec2::Y);
+                outside_contact(centre, closest, radius, fallback)
+            }
+
+            Self::Aabb { min, max } => {
+                let closest = centre.clamp(*min, *max);
+
+                if closest != centre {
+                    return outside_contact(centre, closest, radius, Vec2::Y);
+                }
+
+                // inside, leave through the nearest face
+                let faces = [
+                    (centre.x - min.x, Vec2::NEG_X),
+                    (max.x - centre.x, Vec2::X),
+                    (centre.y - min.y, Vec2::NEG_Y),
+                    (max.y - centre.y, Vec2::Y),
+                ];
+
+                let (distance, normal) = faces
+                    .into_iter()
+                    .min_by(|(a, _), (b, _)| a.total_cmp(b))
+                    .unwrap();
+
+                Some(Contact {
+                    normal,
+                    depth: radius + distance,
+                })
+            }
+
+            Self::Circle {
+                centre: collider_centre,
+                radius: collider_radius,
+            } => {
+                let delta = centre - *collider_centre;
+                let distance = delta.length();
+                let depth = collider_radius + radius - distance;
+
+                (depth > 0.0).then(|| Contact {
+                    normal: delta.try_normalize().unwrap_or(Vec2::Y),
+                    depth,
+                })
+            }
+
+            Self::Polygon(vertices) => polygon_contact(vertices, centre, radius),
+        }
+    }
+
+    /// Line segments tracing the shape, circles use `circle_segments` of them
+    pub fn outline(&self, circle_segments: usize) -> Vec<[Vec2; 2]> {
+        let closed = |points: &[Vec2]| {
+            (0..points.len())
+                .map(|i| [points[i], points[(i + 1) % points.len()]])
+                .collect()
+        };
+
+        match self {
+            Self::Segment { a, b } => vec![[*a, *b]],
+
+            Self::Aabb { min, max } => closed(&[
+                *min,
+                glam::vec2(max.x, min.y),
+                *max,
+                glam::vec2(min.x, max.y),
+            ]),
+
+            Self::Circle { centre, radius } => closed(
+                &(0..circle_segments)
+                    .map(|i| {
+                        let angle = i as f32 / circle_segments as f32 * std::f32::consts::TAU;
+                        *centre + Vec2::from_angle(angle) * *radius
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+
+            Self::Polygon(vertices) => closed(vertices),
+        }
+    }
+}
+
+/// Component of static collider entities
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    pub surface: Surface,
+}
+
+impl Collider {
+    /// Perfectly elastic and frictionless
+    pub fn new(shape: ColliderShape) -> Self {
+        Self {
+            shape,
+            surface: Surface::default(),
+        }
+    }
+
+    /// Pushes a particle with `surface` out of the collider and bounces it if it's
+    /// moving inwards, returns whether they touched
+    pub fn resolve(&self, pos: &mut Vec2, vel: &mut Vec2, radius: f32, surface: Surface) -> bool {
+        let Some(Contact { normal, depth }) = self.shape.contact(*pos, radius) else {
+            return false;
+        };
+
+        *pos += normal * depth;
+        bounce(vel, normal, self.surface.combine(surface));
+
+        true
+    }
+}
+
+/// Reflects the part of `vel` going into a static surface with outward `normal`,
+/// friction can at most stop the sliding
+pub fn bounce(vel: &mut Vec2, normal: Vec2, surface: Surface) {
+    let normal_speed = vel.dot(normal);
+    if normal_speed >= 0.0 {
+        return;
+    }
+
+    let tangent_vel = *vel - normal * normal_speed;
+
+    // impulses per unit mass
+    let normal_impulse = -(1.0 + surface.restitution) * normal_speed;
+    let friction_impulse = (surface.friction * normal_impulse).min(tangent_vel.length());
+
+    *vel += normal * normal_impulse - tangent_vel.normalize_or_zero() * friction_impulse;
+}
+
+/// At least 3 vertices turning the same way at every corner, in either winding order
+pub fn is_convex(vertices: &[Vec2]) -> bool {
+    if vertices.len() < 3 {
+        return false;
+    }
+
+    let turns = (0..vertices.len()).map(|i| {
+        let [a, b, c] = [0, 1, 2].map(|offset| vertices[(i + offset) % vertices.len()]);
+        (b - a).perp_dot(c - b)
+    });
+
+    let (mut left, mut right) = (false, false);
+    for turn in turns {
+        left |= turn > 0.0;
+        right |= turn < 0.0;
+    }
+
+    // all collinear isn't a polygon either
+    left != right
+}
+
+fn closest_on_segment(point: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    let length_sq = ab.length_squared();
+
+    if length_sq == 0.0 {
+        return a;
+    }
+
+    a + ab * ((point - a).dot(ab) / length_sq).clamp(0.0, 1.0)
+}
+
+/// Contact of a circle whose centre lies outside the collider, `closest` being the
+/// nearest point of the collider
+fn outside_contact(centre: Vec2, closest: Vec2, radius: f32, fallback: Vec2) -> Option<Contact> {
+    let delta = centre - closest;
+    let distance = delta.length();
+
+    (distance < radius).then(|| Contact {
+        normal: delta.try_normalize().unwrap_or(fallback),
+        depth: radius - distance,
+    })
+}
+
+fn polygon_contact(vertices: &[Vec2], centre: Vec2, radius: f32) -> Option<Contact> {
+    let edges = || (0..vertices.len()).map(|i| (vertices[i], vertices[(i + 1) % vertices.len()]));
+
+    // twice the signed area, tells which side of each edge is outside
+    let winding = edges().map(|(a, b)| a.perp_dot(b)).sum::<f32>().signum();
+
+    // (signed distance from the edge's line, outward normal)
+    let (distance, normal) = edges()
+        .map(|(a, b)| {
+            let normal = (b - a).perp().normalize_or_zero() * -winding;
+            ((centre - a).dot(normal), normal)
+        })
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))?;
+
+    if distance <= 0.0 {
+        // inside, leave through the nearest edge
+        return Some(Contact {
+            normal,
+            depth: radius - distance,
+        });
+    }
+
+    let closest = edges()
+        .map(|(a, b)| closest_on_segment(centre, a, b))
+        .min_by(|a, b| {
+            a.distance_squared(centre)
+                .total_cmp(&b.distance_squared(centre))
+        })?;
+
+    outside_contact(centre, closest, radius, normal)
+}
@@ -1,16 +1,68 @@
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct InstanceCount(pub i32);
+use legion::Entity;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct InstanceDataPtr(usize);
+use crate::FLOATS_PER_INSTANCE;
+
+/// CPU-side per-instance data (see `FLOATS_PER_INSTANCE` for the layout),
+/// this is what the renderer uploads to the GPU every frame.
+///
+/// Instances are kept densely packed, removing one moves the last instance
+/// into its slot so the entity owning every instance is tracked alongside.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstanceData {
+    data: Vec<f32>,
+    owners: Vec<Entity>,
+}
 
-impl InstanceDataPtr {
-    pub fn new(ptr: *mut f32) -> Self {
-        Self(ptr as _)
+impl InstanceData {
+    pub fn len(&self) -> usize {
+        self.owners.len()
     }
 
-    pub fn get_ptr(&self) -> *mut f32 {
-        self.0 as _
+    pub fn is_empty(&self) -> bool {
+        self.owners.is_empty()
+    }
+
+    /// Appends an instance owned by `entity` and returns its index
+    pub fn push(&mut self, instance: [f32; FLOATS_PER_INSTANCE], entity: Entity) -> usize {
+        self.data.extend(instance);
+        self.owners.push(entity);
+        self.len() - 1
+    }
+
+    /// Removes the instance at `index` by moving the last instance into its
+    /// place, returns the owner of the moved instance if there was one
+    pub fn swap_remove(&mut self, index: usize) -> Option<Entity> {
+        let last = self.len() - 1;
+
+        self.data
+            .copy_within(last * FLOATS_PER_INSTANCE.., index * FLOATS_PER_INSTANCE);
+        self.data.truncate(last * FLOATS_PER_INSTANCE);
+        self.owners.swap_remove(index);
+
+        (index != last).then(|| self.owners[index])
+    }
+
+    pub fn get(&self, index: usize) -> &[f32; FLOATS_PER_INSTANCE] {
+        self.data[index * FLOATS_PER_INSTANCE..][..FLOATS_PER_INSTANCE]
+            .try_into()
+            .unwrap()
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> &mut [f32; FLOATS_PER_INSTANCE] {
+        (&mut self.data[index * FLOATS_PER_INSTANCE..][..FLOATS_PER_INSTANCE])
+            .try_into()
+            .unwrap()
+    }
+
+    pub fn owner(&self, index: usize) -> Entity {
+        self.owners[index]
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.data
     }
 }
 
@@ -23,5 +75,123 @@ pub struct EntityIndex(pub usize);
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Mass(pub f32);
 
+/// How a particle bounces off everything else
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Surface {
+    /// Fraction of the normal relative velocity kept after a bounce, `1.0` is perfectly elastic
+    pub restitution: f32,
+
+    /// Coulomb friction coefficient, limits how much tangential velocity a bounce takes away
+    pub friction: f32,
+}
+
+impl Default for Surface {
+    /// Perfectly elastic and frictionless
+    fn default() -> Self {
+        Self {
+            restitution: 1.0,
+            friction: 0.0,
+        }
+    }
+}
+
+impl Surface {
+    /// Coefficients of a contact between two surfaces. The smaller restitution and the
+    /// larger friction win, so a surface meeting itself keeps its own coefficients and
+    /// the defaults leave the other surface in charge.
+    pub fn combine(self, other: Self) -> Self {
+        Self {
+            restitution: self.restitution.min(other.restitution),
+            friction: self.friction.max(other.friction),
+        }
+    }
+}
+
+/// Force from other particles, such as mutual gravity, accumulated over a step and
+/// cleared after integration. Force fields are applied by the integrator itself.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Force(pub glam::Vec2);
+
+/// How long a particle has existed and how long it's allowed to, in seconds.
+/// The particle is despawned once `age` reaches `duration`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lifetime {
+    pub age: f32,
+    pub duration: f32,
+}
+
+impl Lifetime {
+    pub fn new(duration: f32) -> Self {
+        Self { age: 0.0, duration }
+    }
+}
+
+/// Color and radius at the start and end of a particle's `Lifetime`,
+/// the instance data is interpolated between the two as it ages
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fade {
+    pub start_color: [f32; 3],
+    pub end_color: [f32; 3],
+    pub start_radius: f32,
+    pub end_radius: f32,
+}
+
+/// Entities to be removed at the end of the current step
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DespawnQueue(pub Vec<Entity>);
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct DeltaTime(pub f32);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuadCapacity(pub usize);
+
+/// Times every contact between particles is solved per step, more keep tall piles
+/// from sinking into each other at the cost of speed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContactPasses(pub usize);
+
+/// The only source of randomness in the simulation, seeded so runs can be reproduced
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationRng {
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl SimulationRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Restores a generator that has already produced `word_pos` words
+    pub fn from_state(seed: u64, word_pos: u128) -> Self {
+        let mut rng = Self::new(seed);
+        rng.rng.set_word_pos(word_pos);
+        rng
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn word_pos(&self) -> u128 {
+        self.rng.get_word_pos()
+    }
+}
+
+impl std::ops::Deref for SimulationRng {
+    type Target = ChaCha8Rng;
+
+    fn deref(&self) -> &Self::Target {
+        &self.rng
+    }
+}
+
+impl std::ops::DerefMut for SimulationRng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.rng
+    }
+}
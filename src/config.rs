@@ -0,0 +1,202 @@
+use std::str::FromStr;
+
+use super::*;
+
+pub const HELP: &str = "usage: particle_simulator [OPTIONS]
+
+Opens a window and simulates the particles of a scene, draw particles with the
+left mouse button and erase them with the right one.
+
+options:
+    --scene FILE            load the initial conditions from a scene file
+    --seed N                seed of the simulation, overrides the scene's
+    --width N               window width, defaults to the scene size or 800
+    --height N              window height, defaults to the scene size or 800,
+                            the scene is scaled to fit the window
+    --vsync                 wait for the display before swapping buffers
+    --circle-points N       vertices of each drawn circle [default: 8]
+    --buffer-capacity N     particles the instance buffer holds before growing [default: 1000000]
+    --broad-phase NAME      how collisions are found, quadtree or grid [default: quadtree]
+    --quad-capacity N       points per quadtree node before it splits [default: 32]
+    --radius R              radius of particles drawn with the mouse [default: 10]
+    --rate N                particles per second drawn with the mouse [default: 6000]
+    --lifetime SECONDS      seconds until drawn particles fade out, or none [default: none]
+    --eraser-radius R       radius erased around the cursor by the right mouse button [default: 30]
+    --gravity G             downward acceleration toggled with the G key [default: 500]
+    --snapshot FILE         saved with S and loaded with L, RON when it ends in .ron
+                            [default: snapshot.bin]
+    --timestep SECONDS      fixed simulation step [default: 0.00833]
+    --max-substeps N        steps simulated per frame at most [default: 8]
+    --headless              run the simulation without a window
+    --steps N               steps to simulate in headless mode [default: 1000]
+    -h, --help              print this message";
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub scene: Option<String>,
+    pub seed: Option<u64>,
+
+    /// `None` falls back to the scene size, then to 800
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+
+    pub vsync: bool,
+
+    /// Vertices of the circle mesh every particle is drawn with
+    pub circle_points: usize,
+
+    /// Initial size of the instance buffer in particles, it doubles when full
+    pub buffer_capacity: usize,
+
+    /// `None` keeps the scene's value
+    pub broad_phase: Option<BroadPhaseKind>,
+    pub quad_capacity: Option<usize>,
+
+    /// Radius and particles per second of the left mouse button brush
+    pub radius: f32,
+    pub rate: f32,
+
+    /// Seconds until a particle drawn with the mouse fades out, `None` keeps them forever
+    pub lifetime: Option<f32>,
+
+    pub eraser_radius: f32,
+
+    /// Units per second squared along +y, which points down
+    pub gravity: f32,
+
+    pub snapshot: String,
+
+    pub timestep: f32,
+    pub max_substeps: usize,
+
+    pub headless: bool,
+    pub steps: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scene: None,
+            seed: None,
+            width: None,
+            height: None,
+            vsync: false,
+            circle_points: 8,
+            buffer_capacity: 1_000_000,
+            broad_phase: None,
+            quad_capacity: None,
+            radius: 10.0,
+            rate: 6000.0,
+            lifetime: None,
+            eraser_radius: 30.0,
+            gravity: 500.0,
+            snapshot: "snapshot.bin".to_string(),
+            timestep: 1.0 / 120.0,
+            max_substeps: 8,
+            headless: false,
+            steps: None,
+        }
+    }
+}
+
+impl Config {
+    /// Parses the arguments, without the program name, and rejects nonsensical values
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for `{arg}`"));
+
+            match arg.as_str() {
+                "--scene" => config.scene = Some(value()?),
+                "--seed" => config.seed = Some(parse(&arg, value()?)?),
+                "--width" => config.width = Some(parse(&arg, value()?)?),
+                "--height" => config.height = Some(parse(&arg, value()?)?),
+                "--vsync" => config.vsync = true,
+                "--circle-points" => config.circle_points = parse(&arg, value()?)?,
+                "--buffer-capacity" => config.buffer_capacity = parse(&arg, value()?)?,
+                "--broad-phase" => config.broad_phase = Some(value()?.parse()?),
+                "--quad-capacity" => config.quad_capacity = Some(parse(&arg, value()?)?),
+                "--radius" => config.radius = parse(&arg, value()?)?,
+                "--rate" => config.rate = parse(&arg, value()?)?,
+                "--lifetime" => {
+                    config.lifetime = match value()?.as_str() {
+                        "none" => None,
+                        seconds => Some(parse(&arg, seconds.to_string())?),
+                    }
+                }
+                "--eraser-radius" => config.eraser_radius = parse(&arg, value()?)?,
+                "--gravity" => config.gravity = parse(&arg, value()?)?,
+                "--snapshot" => config.snapshot = value()?,
+                "--timestep" => config.timestep = parse(&arg, value()?)?,
+                "--max-substeps" => config.max_substeps = parse(&arg, value()?)?,
+                "--headless" => config.headless = true,
+                "--steps" => config.steps = Some(parse(&arg, value()?)?),
+                _ => return Err(format!("unknown argument `{arg}`")),
+            }
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let checks = [
+            (
+                self.width.is_none_or(|w| w > 0) && self.height.is_none_or(|h| h > 0),
+                "`--width` and `--height` must be at least 1",
+            ),
+            (
+                self.circle_points >= 3,
+                "`--circle-points` must be at least 3",
+            ),
+            (
+                self.buffer_capacity > 0,
+                "`--buffer-capacity` must be at least 1",
+            ),
+            (
+                self.quad_capacity.is_none_or(|capacity| capacity > 0),
+                "`--quad-capacity` must be at least 1",
+            ),
+            (
+                self.radius.is_finite() && self.radius > 0.0,
+                "`--radius` must be positive",
+            ),
+            (
+                self.rate.is_finite() && self.rate >= 0.0,
+                "`--rate` can't be negative",
+            ),
+            (
+                self.lifetime
+                    .is_none_or(|lifetime| lifetime.is_finite() && lifetime > 0.0),
+                "`--lifetime` must be positive or `none`",
+            ),
+            (
+                self.eraser_radius.is_finite() && self.eraser_radius > 0.0,
+                "`--eraser-radius` must be positive",
+            ),
+            (self.gravity.is_finite(), "`--gravity` must be finite"),
+            (
+                self.timestep.is_finite() && self.timestep > 0.0,
+                "`--timestep` must be positive",
+            ),
+            (self.max_substeps > 0, "`--max-substeps` must be at least 1"),
+            (
+                self.headless || self.steps.is_none(),
+                "`--steps` only applies to `--headless` runs",
+            ),
+        ];
+
+        match checks.into_iter().find(|(ok, _)| !ok) {
+            Some((_, message)) => Err(message.to_string()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse<T: FromStr>(name: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{value}` for `{name}`"))
+}
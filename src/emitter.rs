@@ -0,0 +1,268 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// A value picked uniformly between `min` and `max`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValueRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl ValueRange {
+    pub fn new(min: f32, max: f32) -> Self {
+        Self { min, max }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        Self::new(value, value)
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        if self.min >= self.max {
+            return self.min;
+        }
+
+        rng.random_range(self.min..=self.max)
+    }
+}
+
+/// Spread of particle radii. The variants with a `range` stay within it, a log-normal
+/// one only stays positive.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Distribution {
+    Uniform(ValueRange),
+
+    /// Gaussian, values outside of `range` are drawn again
+    Normal {
+        mean: f32,
+        std_dev: f32,
+        range: ValueRange,
+    },
+
+    /// Gaussian in log space, skewed towards a few large values without an upper bound
+    LogNormal {
+        median: f32,
+        sigma: f32,
+    },
+
+    /// Density proportional to `x^-exponent` over `range`, many small values and
+    /// few large ones for positive exponents
+    PowerLaw {
+        range: ValueRange,
+        exponent: f32,
+    },
+}
+
+/// Draws of a truncated normal distribution before giving up and clamping
+const MAX_REDRAWS: usize = 64;
+
+impl Distribution {
+    pub fn constant(value: f32) -> Self {
+        Self::Uniform(ValueRange::constant(value))
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            Self::Uniform(range) => range.sample(rng),
+
+            Self::Normal {
+                mean,
+                std_dev,
+                range,
+            } => (0..MAX_REDRAWS)
+                .map(|_| mean + std_dev * standard_normal(rng))
+                .find(|value| (range.min..=range.max).contains(value))
+                .unwrap_or(mean.clamp(range.min, range.max)),
+
+            Self::LogNormal { median, sigma } => median * (sigma * standard_normal(rng)).exp(),
+
+            Self::PowerLaw { range, exponent } => {
+                let ValueRange { min, max } = range;
+                let u = rng.random::<f32>();
+
+                // inverse of the cumulative distribution, `x^-1` integrates to a log
+                if (exponent - 1.0).abs() < 1e-6 {
+                    return min * (max / min).powf(u);
+                }
+
+                let power = 1.0 - exponent;
+                (min.powf(power) + u * (max.powf(power) - min.powf(power))).powf(1.0 / power)
+            }
+        }
+    }
+}
+
+/// Box-Muller transform
+fn standard_normal(rng: &mut impl Rng) -> f32 {
+    // `1 - u` keeps the logarithm away from zero
+    let u = 1.0 - rng.random::<f32>();
+    let v = rng.random::<f32>();
+
+    (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+}
+
+/// Area around the emitter position where particles appear
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SpawnShape {
+    Point,
+    Circle {
+        radius: f32,
+    },
+
+    /// From the emitter position to `position + offset`
+    Line {
+        offset: Vec2,
+    },
+
+    /// Centred on the emitter position
+    Rectangle {
+        size: Vec2,
+    },
+}
+
+impl SpawnShape {
+    pub fn sample(&self, rng: &mut impl Rng) -> Vec2 {
+        match *self {
+            Self::Point => Vec2::ZERO,
+
+            Self::Circle { radius } => {
+                // sqrt keeps the density uniform over the area
+                let r = radius * rng.random::<f32>().sqrt();
+                Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU)) * r
+            }
+
+            Self::Line { offset } => offset * rng.random::<f32>(),
+
+            Self::Rectangle { size } => {
+                (glam::vec2(rng.random(), rng.random()) - Vec2::splat(0.5)) * size
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EmissionMode {
+    /// Particles per second
+    Continuous(f32),
+
+    /// `count` particles at once every `interval` seconds, starting right away.
+    /// An infinite interval gives a single burst.
+    Burst { count: usize, interval: f32 },
+}
+
+/// Spawns particles every step, emitters are entities of their own
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Emitter {
+    pub position: Vec2,
+    pub mode: EmissionMode,
+    pub shape: SpawnShape,
+
+    /// Angle of the velocity cone's axis in radians, `0.0` points along +x
+    pub direction: f32,
+
+    /// Full opening angle of the velocity cone in radians
+    pub spread: f32,
+
+    pub speed: ValueRange,
+    pub radius: Distribution,
+
+    /// `None` derives the mass from the radius and the material's density
+    pub mass: Option<ValueRange>,
+
+    /// Red, green and blue are picked independently, `None` picks from the material's palette
+    pub color: Option<[ValueRange; 3]>,
+
+    /// Emitted particles shrink away over this many seconds, `None` keeps them forever
+    pub lifetime: Option<f32>,
+
+    /// Color particles with a lifetime fade to as they shrink
+    pub fade_color: [f32; 3],
+
+    pub material: MaterialId,
+
+    /// Fractional particles carried over for continuous emission,
+    /// time until the next burst otherwise
+    pending: f32,
+}
+
+impl Emitter {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            position,
+            mode: EmissionMode::Continuous(100.0),
+            shape: SpawnShape::Point,
+            direction: 0.0,
+            spread: std::f32::consts::TAU,
+            speed: ValueRange::new(0.0, 50.0),
+            radius: Distribution::constant(5.0),
+            mass: None,
+            color: Some([ValueRange::new(0.0, 1.0); 3]),
+            lifetime: None,
+            fade_color: [0.0; 3],
+            material: MaterialId::default(),
+            pending: 0.0,
+        }
+    }
+
+    /// What `advance` carried over from previous steps, kept by snapshots
+    pub fn pending(&self) -> f32 {
+        self.pending
+    }
+
+    pub fn set_pending(&mut self, pending: f32) {
+        self.pending = pending;
+    }
+
+    /// Number of particles to emit after `dt` more seconds
+    pub fn advance(&mut self, dt: f32) -> usize {
+        match self.mode {
+            EmissionMode::Continuous(rate) => {
+                self.pending += rate * dt;
+
+                let count = self.pending.floor();
+                self.pending -= count;
+
+                count as usize
+            }
+
+            EmissionMode::Burst { count, interval } => {
+                self.pending -= dt;
+
+                if self.pending > 0.0 {
+                    return 0;
+                }
+
+                self.pending += interval;
+                count
+            }
+        }
+    }
+
+    /// Instance data, velocity and mass of a new particle, `material` is the one
+    /// `self.material` points to
+    pub fn sample(
+        &self,
+        material: &Material,
+        rng: &mut impl Rng,
+    ) -> ([f32; FLOATS_PER_INSTANCE], Vec2, f32) {
+        let pos = self.position + self.shape.sample(rng);
+
+        let angle = self.direction + (rng.random::<f32>() - 0.5) * self.spread;
+        let velocity = Vec2::from_angle(angle) * self.speed.sample(rng);
+
+        let radius = self.radius.sample(rng);
+        let mass = match self.mass {
+            Some(mass) => mass.sample(rng),
+            None => material.mass(radius),
+        };
+
+        let [red, green, blue] = match self.color {
+            Some(color) => color.map(|channel| channel.sample(rng)),
+            None => material.color(rng),
+        };
+
+        ([pos.x, pos.y, radius, red, green, blue], velocity, mass)
+    }
+}
@@ -0,0 +1,62 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// A global force acting on every particle
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ForceField {
+    /// Uniform acceleration, the force scales with mass
+    Gravity(Vec2),
+
+    /// `-k * v`
+    LinearDrag(f32),
+
+    /// `-k * |v| * v`
+    QuadraticDrag(f32),
+
+    /// Linear drag relative to moving air, pushes particles towards `velocity`
+    Wind { velocity: Vec2, drag: f32 },
+}
+
+impl ForceField {
+    pub fn force(&self, vel: Vec2, mass: f32) -> Vec2 {
+        match *self {
+            Self::Gravity(accel) => accel * mass,
+            Self::LinearDrag(k) => -k * vel,
+            Self::QuadraticDrag(k) => -k * vel.length() * vel,
+            Self::Wind { velocity, drag } => -drag * (vel - velocity),
+        }
+    }
+}
+
+/// Force fields applied to all particles every step, stored as a resource
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ForceFields(pub Vec<ForceField>);
+
+impl ForceFields {
+    pub fn force(&self, vel: Vec2, mass: f32) -> Vec2 {
+        self.0.iter().map(|field| field.force(vel, mass)).sum()
+    }
+}
+
+/// Mutual gravitational attraction between all particles, approximated with
+/// a Barnes–Hut quadtree. Disabled while `constant` is zero.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gravitation {
+    pub constant: f32,
+
+    /// Opening angle, larger is faster but less accurate
+    pub theta: f32,
+
+    /// Added to every distance to keep close encounters finite
+    pub softening: f32,
+}
+
+impl Default for Gravitation {
+    fn default() -> Self {
+        Self {
+            constant: 0.0,
+            theta: 0.5,
+            softening: 1.0,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Numerical scheme used to advance positions and velocities, stored as a resource
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Integrator {
+    ExplicitEuler,
+    #[default]
+    SemiImplicitEuler,
+    VelocityVerlet,
+    Rk4,
+}
+
+impl Integrator {
+    /// Advances `pos` and `vel` by `dt` seconds, `accel` returns the
+    /// acceleration for a given position and velocity
+    pub fn step(
+        self,
+        pos: Vec2,
+        vel: Vec2,
+        dt: f32,
+        accel: impl Fn(Vec2, Vec2) -> Vec2,
+    ) -> (Vec2, Vec2) {
+        match self {
+            Self::ExplicitEuler => (pos + vel * dt, vel + accel(pos, vel) * dt),
+
+            Self::SemiImplicitEuler => {
+                let vel = vel + accel(pos, vel) * dt;
+                (pos + vel * dt, vel)
+            }
+
+            Self::VelocityVerlet => {
+                let half_vel = vel + accel(pos, vel) * (dt * 0.5);
+                let pos = pos + half_vel * dt;
+
+                (pos, half_vel + accel(pos, half_vel) * (dt * 0.5))
+            }
+
+            Self::Rk4 => {
+                let (k1_pos, k1_vel) = (vel, accel(pos, vel));
+
+                let (k2_pos, k2_vel) = {
+                    let vel = vel + k1_vel * (dt * 0.5);
+                    (vel, accel(pos + k1_pos * (dt * 0.5), vel))
+                };
+
+                let (k3_pos, k3_vel) = {
+                    let vel = vel + k2_vel * (dt * 0.5);
+                    (vel, accel(pos + k2_pos * (dt * 0.5), vel))
+                };
+
+                let (k4_pos, k4_vel) = {
+                    let vel = vel + k3_vel * dt;
+                    (vel, accel(pos + k3_pos * dt, vel))
+                };
+
+                (
+                    pos + (k1_pos + 2.0 * k2_pos + 2.0 * k3_pos + k4_pos) * (dt / 6.0),
+                    vel + (k1_vel + 2.0 * k2_vel + 2.0 * k3_vel + k4_vel) * (dt / 6.0),
+                )
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "euler" => Ok(Self::ExplicitEuler),
+            "semi-implicit-euler" => Ok(Self::SemiImplicitEuler),
+            "verlet" => Ok(Self::VelocityVerlet),
+            "rk4" => Ok(Self::Rk4),
+            _ => Err(format!(
+                "unknown integrator `{s}`, expected one of euler, semi-implicit-euler, verlet, rk4"
+            )),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+pub mod boundary;
+pub mod broad_phase;
+pub mod colliders;
+pub mod components;
+pub mod config;
+pub mod emitter;
+pub mod forces;
+pub mod integrator;
+pub mod material;
+pub mod quadtree;
+pub mod scene;
+pub mod simulation;
+pub mod snapshot;
+pub mod systems;
+pub mod timestep;
+pub mod utils;
+
+use boundary::Domain;
+use broad_phase::{BroadPhase, BroadPhaseKind, SpatialGrid};
+use colliders::Collider;
+use components::*;
+use emitter::Emitter;
+use forces::{ForceFields, Gravitation};
+use material::{Material, MaterialId, Materials};
+use quadtree::*;
+
+use glam::Vec2;
+use legion::*;
+
+pub use integrator::Integrator;
+pub use scene::Scene;
+pub use simulation::Simulation;
+pub use snapshot::Snapshot;
+pub use timestep::FixedTimestep;
+
+// x, y, radius, red, green, blue
+pub const FLOATS_PER_INSTANCE: usize = 6;
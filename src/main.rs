@@ -2,45 +2,128 @@ use std::time::Instant;
 
 use glfw::{Context, WindowHint};
 
-mod components;
-mod quadtree;
+mod render;
 mod shader;
-mod systems;
-mod utils;
 
 use glow::HasContext;
 use shader::Shader;
-use systems as sys;
 
-use components::*;
-use quadtree::*;
+use particle_simulator::components::QuadCapacity;
+use particle_simulator::config::{Config, HELP};
+use particle_simulator::emitter::{Distribution, EmissionMode, Emitter, ValueRange};
+use particle_simulator::forces::{ForceField, ForceFields};
+use particle_simulator::{utils, FixedTimestep, Scene, Simulation, Snapshot, FLOATS_PER_INSTANCE};
+use render::{BUFFER_ACCESS_FLAGS, INSTANCE_DATA_STRIDE};
 
-use glam::Vec2;
-use legion::*;
+// line segments approximating circular colliders
+const COLLIDER_CIRCLE_SEGMENTS: usize = 48;
 
-const WIDTH: u32 = 800;
-const HEIGHT: u32 = 800;
+/// What used to be spawned per frame while holding the left mouse button
+fn brush_emitter(position: glam::Vec2, config: &Config) -> Emitter {
+    let mut emitter = Emitter::new(position);
 
-const POINT_COUNT: usize = 8;
-const BUFFER_ACCESS_FLAGS: u32 =
-    glow::MAP_WRITE_BIT | glow::MAP_READ_BIT | glow::MAP_PERSISTENT_BIT | glow::MAP_COHERENT_BIT;
+    emitter.mode = EmissionMode::Continuous(config.rate);
+    emitter.speed = ValueRange::new(0.0, 30.0);
+    emitter.radius = Distribution::constant(config.radius);
+    emitter.lifetime = config.lifetime;
 
-// x, y, radius, red, green, blue
-const FLOATS_PER_INSTANCE: usize = 6;
-const INSTANCE_DATA_STRIDE: usize = std::mem::size_of::<f32>() * FLOATS_PER_INSTANCE;
+    emitter
+}
+
+fn fountain_emitter(position: glam::Vec2) -> Emitter {
+    let mut emitter = Emitter::new(position);
+
+    emitter.mode = EmissionMode::Continuous(200.0);
+    emitter.direction = -std::f32::consts::FRAC_PI_2;
+    emitter.spread = 0.5;
+    emitter.speed = ValueRange::new(150.0, 250.0);
+    emitter.radius = Distribution::Uniform(ValueRange::new(3.0, 6.0));
+    emitter.color = Some([
+        ValueRange::new(0.2, 0.4),
+        ValueRange::new(0.5, 0.8),
+        ValueRange::constant(1.0),
+    ]);
+    emitter.lifetime = Some(4.0);
+
+    emitter
+}
+
+/// Cursor position in simulation coordinates
+fn cursor_in_domain(window: &glfw::Window, domain: glam::Vec2) -> glam::Vec2 {
+    utils::window_to_domain(window.get_size(), window.get_cursor_pos(), domain)
+}
 
-const INITIAL_BUFFER_FLOAT_CAPACITY: usize = 1_000_000;
+/// Steps the simulation as fast as possible without opening a window
+fn run_headless(mut simulation: Simulation, steps: usize) {
+    let start = Instant::now();
+
+    for _ in 0..steps {
+        simulation.step(simulation.timestep.step);
+    }
+
+    let elapsed = start.elapsed();
+    println!(
+        "{steps} steps in {elapsed:.2?} ({:.2?} per step), {} particles left",
+        elapsed / steps.max(1) as u32,
+        simulation.len()
+    );
+}
 
 fn main() {
-    let mut world = World::default();
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{HELP}");
+        return;
+    }
+
+    let config = Config::from_args(args).unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{HELP}");
+        std::process::exit(2);
+    });
+
+    let scene = config.scene.as_ref().map(|path| {
+        let mut scene = Scene::from_file(path).unwrap_or_else(|e| {
+            eprintln!("failed to load {path}:\n{e}");
+            std::process::exit(1);
+        });
+
+        scene.seed = config.seed.or(scene.seed);
+        scene
+    });
+
+    let (scene_width, scene_height) = scene.as_ref().map_or((800, 800), |scene| {
+        let [width, height] = *scene.size.get_ref();
+        (width as u32, height as u32)
+    });
+
+    let width = config.width.unwrap_or(scene_width);
+    let height = config.height.unwrap_or(scene_height);
+
+    // without a scene the domain matches the window it starts in, resizing doesn't change it
+    let mut simulation = match scene {
+        Some(scene) => scene.into_simulation(),
+        None => Simulation::new(
+            glam::vec2(width as _, height as _),
+            config.seed.unwrap_or_else(rand::random),
+        ),
+    };
+
+    println!("seed: {}", simulation.seed());
+    simulation.timestep = FixedTimestep::new(config.timestep, config.max_substeps);
 
-    let mut resources = Resources::default();
-    let mut schedule = Schedule::builder()
-        .add_system(sys::update_positions_system())
-        .add_system(sys::check_wall_collision_system())
-        .build();
+    if let Some(broad_phase) = config.broad_phase {
+        simulation.resources.insert(broad_phase);
+    }
 
-    resources.insert(InstanceCount(0));
+    if let Some(capacity) = config.quad_capacity {
+        simulation.resources.insert(QuadCapacity(capacity));
+    }
+
+    if config.headless {
+        run_headless(simulation, config.steps.unwrap_or(1000));
+        return;
+    }
 
     let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
 
@@ -49,11 +132,9 @@ fn main() {
     glfw.window_hint(WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
 
     let (mut window, event) = glfw
-        .create_window(WIDTH, HEIGHT, "Rendering text", glfw::WindowMode::Windowed)
+        .create_window(width, height, "Rendering text", glfw::WindowMode::Windowed)
         .unwrap();
 
-    resources.insert(window.get_size());
-
     window.set_cursor_pos_polling(true);
     window.set_key_polling(true);
     window.set_mouse_button_polling(true);
@@ -64,12 +145,15 @@ fn main() {
     let gl =
         unsafe { glow::Context::from_loader_function(|s| window.get_proc_address(s) as *const _) };
 
-    glfw.set_swap_interval(glfw::SwapInterval::None);
-    unsafe { gl.viewport(0, 0, window.get_size().0, window.get_size().1) };
+    glfw.set_swap_interval(if config.vsync {
+        glfw::SwapInterval::Sync(1)
+    } else {
+        glfw::SwapInterval::None
+    });
     window.set_size_polling(true);
 
     let (vao, vbo, ebo);
-    let (vertices, indices) = utils::generate_circle(POINT_COUNT as _);
+    let (vertices, indices) = utils::generate_circle(config.circle_points as _);
 
     unsafe {
         vao = gl.create_vertex_array().unwrap();
@@ -115,8 +199,7 @@ fn main() {
     // instancing
     let mut instance_vbo;
     let mut instance_data_ptr;
-    let mut instance_data_offset = 0;
-    let mut buffer_capacity = INITIAL_BUFFER_FLOAT_CAPACITY;
+    let mut buffer_capacity = config.buffer_capacity;
 
     unsafe {
         instance_vbo = gl.create_buffer().unwrap();
@@ -136,8 +219,7 @@ fn main() {
             BUFFER_ACCESS_FLAGS,
         ) as *mut f32;
 
-        utils::setup_instance_attributes(&gl);
-        resources.insert(InstanceDataPtr::new(instance_data_ptr));
+        render::setup_instance_attributes(&gl);
 
         // unbind
         gl.bind_vertex_array(None);
@@ -146,32 +228,44 @@ fn main() {
     let shader = Shader::from_str(&gl, include_str!("shader.glsl"), "vertex", "fragment")
         .expect("Failed to load shader");
 
-    let orthographic_uniform = |(width, height)| unsafe {
-        shader.use_shader();
-        gl.uniform_matrix_4_f32_slice(
-            Some(&shader.get_uniform_location("ortho").unwrap()),
-            false,
-            &glam::Mat4::orthographic_rh_gl(0.0, width as _, height as _, 0.0, -1.0, 1.0)
-                .to_cols_array(),
-        );
+    let collider_shader = Shader::from_str(
+        &gl,
+        include_str!("shader.glsl"),
+        "collider_vertex",
+        "collider_fragment",
+    )
+    .expect("Failed to load collider shader");
+
+    let (collider_vao, collider_vbo) = unsafe { render::create_line_vao(&gl) };
+
+    // shows the whole domain in the window, keeping its aspect ratio
+    let fit_view = |window_size, domain: glam::Vec2| unsafe {
+        let (x, y, width, height) = utils::letterbox(window_size, domain);
+        gl.viewport(x, y, width, height);
+
+        for shader in [&shader, &collider_shader] {
+            shader.use_shader();
+            gl.uniform_matrix_4_f32_slice(
+                Some(&shader.get_uniform_location("ortho").unwrap()),
+                false,
+                &glam::Mat4::orthographic_rh_gl(0.0, domain.x, domain.y, 0.0, -1.0, 1.0)
+                    .to_cols_array(),
+            );
+        }
     };
 
-    orthographic_uniform(window.get_size());
+    fit_view(window.get_size(), simulation.domain().size);
 
-    let quad_capacity = 32;
-    let mut mouse_down = false;
-    let particle_radius: f32 = 10.0;
+    // emitter following the cursor while the left mouse button is held
+    let mut brush = None;
+    let mut eraser_down = false;
 
     let mut clock = Instant::now();
     while !window.should_close() {
         let dt = clock.elapsed().as_nanos() as f32 / 1e9;
         clock = Instant::now();
 
-        println!(
-            "FPS: {:.0}, {} particles",
-            1.0 / dt,
-            resources.get::<InstanceCount>().unwrap().0
-        );
+        println!("FPS: {:.0}, {} particles", 1.0 / dt, simulation.len());
 
         glfw.poll_events();
 
@@ -182,99 +276,120 @@ fn main() {
                 window.set_should_close(true)
             }
 
-            WindowEvent::Size(width, height) => {
-                unsafe { gl.viewport(0, 0, width, height) };
-                orthographic_uniform((width, height));
-                resources.insert(window.get_size());
+            WindowEvent::Size(width, height) => fit_view((width, height), simulation.domain().size),
+
+            WindowEvent::Key(glfw::Key::G, _, glfw::Action::Press, _) => {
+                let mut fields = simulation.resources.get_mut::<ForceFields>().unwrap();
+                let gravity = ForceField::Gravity(glam::vec2(0.0, config.gravity));
+
+                // toggle gravity
+                if fields.0.contains(&gravity) {
+                    fields.0.retain(|field| *field != gravity);
+                } else {
+                    fields.0.push(gravity);
+                }
+            }
+
+            WindowEvent::Key(glfw::Key::S, _, glfw::Action::Press, _) => {
+                match Snapshot::capture(&simulation).save(&config.snapshot) {
+                    Ok(()) => println!("saved snapshot to {}", config.snapshot),
+                    Err(e) => eprintln!("failed to save snapshot: {e}"),
+                }
+            }
+
+            WindowEvent::Key(glfw::Key::L, _, glfw::Action::Press, _) => {
+                match Snapshot::load(&config.snapshot) {
+                    Ok(snapshot) => {
+                        let timestep = simulation.timestep;
+                        simulation = snapshot.restore();
+                        simulation.timestep = timestep;
+
+                        fit_view(window.get_size(), snapshot.domain.size);
+                    }
+
+                    Err(e) => eprintln!("failed to load snapshot: {e}"),
+                }
+            }
+
+            WindowEvent::Key(glfw::Key::E, _, glfw::Action::Press, _) => {
+                let cursor = cursor_in_domain(&window, simulation.domain().size);
+                simulation.add_emitter(fountain_emitter(cursor));
+            }
+
+            WindowEvent::Key(glfw::Key::X, _, glfw::Action::Press, _) => {
+                simulation.clear_emitters();
+                brush = None;
             }
 
             WindowEvent::MouseButton(glfw::MouseButtonLeft, glfw::Action::Press, _) => {
-                mouse_down = true
+                let cursor = cursor_in_domain(&window, simulation.domain().size);
+                brush = Some(simulation.add_emitter(brush_emitter(cursor, &config)));
             }
 
             WindowEvent::MouseButton(glfw::MouseButtonLeft, glfw::Action::Release, _) => {
-                mouse_down = false
+                if let Some(entity) = brush.take() {
+                    simulation.world.remove(entity);
+                }
+            }
+
+            WindowEvent::MouseButton(glfw::MouseButtonRight, glfw::Action::Press, _) => {
+                eraser_down = true
+            }
+
+            WindowEvent::MouseButton(glfw::MouseButtonRight, glfw::Action::Release, _) => {
+                eraser_down = false
             }
 
             _ => {}
         });
 
-        // let ptr = resources.get::<InstanceDataPtr>().unwrap().get_ptr();
-        // let mut qt = quadtree::QuadTree::<usize>::new(
-        //     quad_capacity,
-        //     Rect {
-        //         left: 0.,
-        //         top: 0.,
-        //         width: window.get_size().0 as _,
-        //         height: window.get_size().1 as _,
-        //     },
-        // );
-
-        // <&EntityIndex>::query().for_each(&world, |id| {
-            // let [x, y, r, ..] = utils::get_entity(id.0, ptr);
-            // qt.push((glam::vec2(*x, *y), *r, id.0));
-        // });
-
-        // resources.insert(qt);
-
-        if mouse_down {
-            for _ in 0..100 {
-                if instance_data_offset / 3 < buffer_capacity {
-                    let v_x: f32 = rand::random_range(-30.0..30.0);
-                    let v_y: f32 = rand::random_range(-30.0..30.0);
-
-                    let (x, y) = window.get_cursor_pos();
-                    unsafe {
-                        let r = rand::random_range(0.0..=1.0);
-                        let g = rand::random_range(0.0..=1.0);
-                        let b = rand::random_range(0.0..=1.0);
-
-                        // copy position and radius data to GPU
-                        let entity_data: [f32; FLOATS_PER_INSTANCE] =
-                            [x as _, y as _, particle_radius, r, g, b];
-
-                        std::ptr::copy_nonoverlapping(
-                            entity_data.as_ptr(),
-                            instance_data_ptr.add(instance_data_offset),
-                            entity_data.len(),
-                        )
-                    };
-
-                    instance_data_offset += FLOATS_PER_INSTANCE;
-
-                    world.push((
-                        // used as pointer offset in systems
-                        EntityIndex(resources.get::<InstanceCount>().unwrap().0 as _),
-                        Velocity(glam::vec2(v_x, v_y)),
-                        Mass(particle_radius.powi(2)),
-                    ));
-
-                    resources.get_mut::<InstanceCount>().unwrap().0 += 1;
-                } else {
-                    let old_capacity = buffer_capacity;
-                    buffer_capacity *= 2;
-
-                    unsafe {
-                        utils::reallocate_instance_vbo(
-                            &gl,
-                            buffer_capacity,
-                            old_capacity,
-                            &mut instance_data_ptr,
-                            &mut instance_vbo,
-                            vao,
-                        )
-                    };
-
-                    // update the old pointer
-                    resources.insert(InstanceDataPtr::new(instance_data_ptr));
-                }
+        let cursor = cursor_in_domain(&window, simulation.domain().size);
+        if let Some(mut entry) = brush.and_then(|entity| simulation.world.entry(entity)) {
+            entry.get_component_mut::<Emitter>().unwrap().position = cursor;
+        }
+
+        if eraser_down {
+            for entity in simulation.particles_within(cursor, config.eraser_radius) {
+                simulation.despawn(entity);
+            }
+        }
+
+        let alpha = simulation.advance(dt);
+
+        let instances = simulation.instances();
+
+        if instances.len() > buffer_capacity {
+            let old_capacity = buffer_capacity;
+            while instances.len() > buffer_capacity {
+                buffer_capacity *= 2;
             }
+
+            unsafe {
+                render::reallocate_instance_vbo(
+                    &gl,
+                    buffer_capacity,
+                    old_capacity,
+                    &mut instance_data_ptr,
+                    &mut instance_vbo,
+                    vao,
+                )
+            };
         }
 
-        resources.insert(DeltaTime(dt));
-        schedule.execute(&mut world, &mut resources);
+        // copy position, radius and color data to GPU
+        simulation.write_interpolated(alpha, unsafe {
+            std::slice::from_raw_parts_mut(instance_data_ptr, instances.len() * FLOATS_PER_INSTANCE)
+        });
+
+        let collider_lines = simulation
+            .colliders()
+            .iter()
+            .flat_map(|collider| collider.shape.outline(COLLIDER_CIRCLE_SEGMENTS))
+            .collect::<Vec<_>>();
 
         unsafe {
+            let collider_vertices = render::upload_lines(&gl, collider_vbo, &collider_lines);
+
             gl.clear_color(0.01, 0.01, 0.01, 1.0);
             gl.clear(glow::COLOR_BUFFER_BIT);
 
@@ -286,8 +401,13 @@ fn main() {
                 indices.len() as _,
                 glow::UNSIGNED_INT,
                 0 as _,
-                resources.get::<InstanceCount>().unwrap().0,
+                instances.len() as _,
             );
+
+            collider_shader.use_shader();
+
+            gl.bind_vertex_array(Some(collider_vao));
+            gl.draw_arrays(glow::LINES, 0, collider_vertices as _);
         }
 
         window.swap_buffers();
@@ -0,0 +1,100 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// What particles are made of, looked up through the `MaterialId` component
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Material {
+    /// Mass per unit of area, particles weigh `density * PI * radius^2`
+    pub density: f32,
+
+    pub surface: Surface,
+
+    /// Colors particles of the material pick from at random, never empty
+    pub palette: Vec<[f32; 3]>,
+
+    /// How hard nearby particles of this material pull on each other, as the relative
+    /// acceleration of a pair, `0.0` for none. See `COHESION_RANGE` for how near.
+    pub cohesion: f32,
+}
+
+/// Cohesion acts while the centres are closer than this many times the sum of the radii
+pub const COHESION_RANGE: f32 = 1.25;
+
+impl Default for Material {
+    /// White, perfectly elastic, frictionless and of unit density
+    fn default() -> Self {
+        Self {
+            density: 1.0,
+            surface: Surface::default(),
+            palette: vec![[1.0; 3]],
+            cohesion: 0.0,
+        }
+    }
+}
+
+impl Material {
+    pub fn mass(&self, radius: f32) -> f32 {
+        self.density * std::f32::consts::PI * radius * radius
+    }
+
+    pub fn color(&self, rng: &mut impl Rng) -> [f32; 3] {
+        self.palette[rng.random_range(0..self.palette.len())]
+    }
+}
+
+/// Component pointing into `Materials`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MaterialId(pub usize);
+
+/// Every material of a simulation by name, `"default"` always exists and has id 0
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Materials(Vec<(String, Material)>);
+
+impl Default for Materials {
+    fn default() -> Self {
+        Self(vec![("default".to_string(), Material::default())])
+    }
+}
+
+impl Materials {
+    /// Adds a material or replaces the one with the same name, particles of the old
+    /// material switch to the new one
+    pub fn insert(&mut self, name: &str, material: Material) -> MaterialId {
+        if let Some(id) = self.id(name) {
+            self.0[id.0].1 = material;
+            return id;
+        }
+
+        self.0.push((name.to_string(), material));
+        MaterialId(self.0.len() - 1)
+    }
+
+    pub fn id(&self, name: &str) -> Option<MaterialId> {
+        self.0.iter().position(|(n, _)| n == name).map(MaterialId)
+    }
+
+    pub fn get(&self, MaterialId(id): MaterialId) -> &Material {
+        &self.0[id].1
+    }
+
+    pub fn name(&self, MaterialId(id): MaterialId) -> &str {
+        &self.0[id].0
+    }
+
+    /// Ordered by id
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Material)> {
+        self.0
+            .iter()
+            .map(|(name, material)| (name.as_str(), material))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
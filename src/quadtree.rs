@@ -1,6 +1,10 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
 use glam::Vec2;
+use rayon::prelude::*;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Rect {
     pub left: f32,
     pub top: f32,
@@ -8,37 +12,139 @@ pub struct Rect {
     pub height: f32,
 }
 
-/// Make sure not to put a big object as the generic
-/// type parameter since there's a lot of cloning.
+/// Nodes this deep don't split any further, so points sharing a position pile up
+/// in one node instead of subdividing forever
+pub const MAX_DEPTH: usize = 24;
+
+/// Parts of the tree with at least this many points to place are built on
+/// separate threads by `par_rebuild`
+const PARALLEL_BUILD_MIN: usize = 4096;
+
+/// Marks the missing children of a leaf
+const NONE: usize = usize::MAX;
+
+/// A loose quadtree of circles. Every circle is stored exactly once, in the node
+/// its centre falls in, and each node's boundary is widened by the largest radius
+/// below it when searching, so circles straddling node edges are still found.
+///
+/// Nodes live in a flat array and refer to their children by index. Neither they
+/// nor the points of each node are freed by `clear`, so once a tree has been
+/// refilled a few times building it doesn't allocate.
 #[derive(Debug, Clone)]
-pub struct QuadTree<T: Clone> {
-    boundary: Rect,
+pub struct QuadTree<T> {
     capacity: usize,
-    children: Option<Box<[QuadTree<T>; 4]>>,
 
-    /// (Position, Radius, Data)
-    points: Vec<Option<(Vec2, f32, T)>>,
+    /// The root comes first, children are stored four at a time
+    nodes: Vec<Node>,
+
+    /// (Position, Radius, Data) of the points held by each node, indexed like
+    /// `nodes`. Can be longer than `nodes` after a `clear`.
+    points: Vec<Vec<(Vec2, f32, T)>>,
+
+    /// The first of four `nodes` given up by merges, to be reused before growing
+    free_nodes: Vec<usize>,
 
+    /// A tree per quarter for `par_rebuild` to build in. Kept so their memory is
+    /// reused by the next rebuild.
+    scratch: Vec<Scratch<T>>,
 }
 
-impl<T: Clone> QuadTree<T> {
+/// Subtree built by `par_rebuild` and the points going into it
+type Scratch<T> = (QuadTree<T>, Vec<(Vec2, f32, T)>);
+
+#[derive(Debug, Clone)]
+struct Node {
+    boundary: Rect,
+    depth: usize,
+
+    /// Index of the first of the four children, `NONE` for a leaf
+    children: usize,
+
+    /// Points held by this node and everything below it
+    len: usize,
+
+    /// Largest radius of the points in this node and below it, circles
+    /// can reach this far out of the boundary
+    max_radius: f32,
+
+    /// Total mass and centre of mass of this node and everything below it,
+    /// only valid after calling `compute_mass_distribution`
+    mass: f32,
+    centre_of_mass: Vec2,
+}
+
+impl Node {
+    fn new(boundary: Rect, depth: usize) -> Self {
+        Self {
+            boundary,
+            depth,
+            children: NONE,
+            len: 0,
+            max_radius: 0.0,
+            mass: 0.0,
+            centre_of_mass: Vec2::ZERO,
+        }
+    }
+
+    /// Which of the four children `pos` belongs in, in the order they're stored
+    fn quadrant(&self, pos: Vec2) -> usize {
+        let Rect {
+            left,
+            top,
+            width,
+            height,
+        } = self.boundary;
+
+        let right = pos.x >= left + width * 0.5;
+        let bottom = pos.y >= top + height * 0.5;
+
+        right as usize + 2 * bottom as usize
+    }
+}
+
+impl<T> QuadTree<T> {
     pub fn new(capacity: usize, boundary: Rect) -> Self {
         Self {
             capacity,
-            boundary,
-            points: Vec::with_capacity(capacity),
-            children: None,
+            nodes: vec![Node::new(boundary, 0)],
+            points: vec![vec![]],
+            free_nodes: vec![],
+            scratch: vec![],
         }
     }
 
-    #[allow(dead_code)]
+    /// Removes every point and makes the tree cover `boundary`, keeping the memory
+    /// so it can be refilled without allocating
+    pub fn clear(&mut self, boundary: Rect) {
+        self.nodes.clear();
+        self.nodes.push(Node::new(boundary, 0));
+        self.points.iter_mut().for_each(Vec::clear);
+        self.free_nodes.clear();
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
     pub fn len(&self) -> usize {
-        self.points.len()
-            + self
-                .children
-                .as_ref()
-                .map(|i| i.iter().map(|i| i.len()).sum())
-                .unwrap_or(0)
+        self.nodes[0].len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The points held by `node` itself
+    fn points_of(&self, node: usize) -> impl Iterator<Item = &(Vec2, f32, T)> + '_ {
+        self.points[node].iter()
+    }
+
+    /// The four children of `node`, empty for a leaf
+    fn children_of(&self, node: usize) -> std::ops::Range<usize> {
+        match self.nodes[node].children {
+            NONE => 0..0,
+            first => first..first + 4,
+        }
     }
 
     // Depth should be 0
@@ -70,109 +176,613 @@ impl<T: Clone> QuadTree<T> {
     //     }
     // }
 
-    /// Get all the points which lie inside the specified area
-    pub fn query(&self, circle_centre: Vec2, circle_radius: f32) -> Vec<T> {
-        let mut found = vec![];
+    /// Like `query` but hands every point to `visit` instead of collecting them
+    pub fn for_each_in_circle(
+        &self,
+        circle_centre: Vec2,
+        circle_radius: f32,
+        visit: &mut impl FnMut(Vec2, f32, &T),
+    ) {
+        self.visit_circle(0, circle_centre, circle_radius, visit);
+    }
+
+    fn visit_circle(
+        &self,
+        node: usize,
+        circle_centre: Vec2,
+        circle_radius: f32,
+        visit: &mut impl FnMut(Vec2, f32, &T),
+    ) {
+        let Node {
+            boundary,
+            len,
+            max_radius,
+            ..
+        } = &self.nodes[node];
 
-        if !cr_intersection(circle_centre, circle_radius, &self.boundary) {
-            return found;
+        if *len == 0 || !cr_intersection(circle_centre, circle_radius + max_radius, boundary) {
+            return;
         }
 
-        found.extend(
-            self.points
-                .iter()
-                .filter_map(|i| (*i).clone())
-                .filter(|(i_circle_centre, i_radius, _)| {
-                    cc_intersection(*i_circle_centre, *i_radius, circle_centre, circle_radius)
-                })
-                .map(|(_, _, data)| data),
-        );
+        for (centre, radius, data) in self.points_of(node) {
+            if cc_intersection(*centre, *radius, circle_centre, circle_radius) {
+                visit(*centre, *radius, data);
+            }
+        }
 
-        if let Some(children) = &self.children {
-            children
-                .iter()
-                .map(|c| c.query(circle_centre, circle_radius))
-                .for_each(|i| found.extend(i));
+        for child in self.children_of(node) {
+            self.visit_circle(child, circle_centre, circle_radius, visit);
         }
+    }
 
-        found
+    /// Like `query_rect` but hands every point to `visit` instead of collecting them
+    pub fn for_each_in_rect(&self, rect: &Rect, visit: &mut impl FnMut(Vec2, f32, &T)) {
+        self.visit_rect(0, rect, visit);
+    }
+
+    fn visit_rect(&self, node: usize, rect: &Rect, visit: &mut impl FnMut(Vec2, f32, &T)) {
+        let Node {
+            boundary,
+            len,
+            max_radius: reach,
+            ..
+        } = &self.nodes[node];
+
+        let grown = Rect {
+            left: rect.left - reach,
+            top: rect.top - reach,
+            width: rect.width + 2.0 * reach,
+            height: rect.height + 2.0 * reach,
+        };
+
+        if *len == 0 || !rr_intersection(&grown, boundary) {
+            return;
+        }
+
+        for (centre, radius, data) in self.points_of(node) {
+            if rect_distance_sq(*centre, rect) <= radius * radius {
+                visit(*centre, *radius, data);
+            }
+        }
+
+        for child in self.children_of(node) {
+            self.visit_rect(child, rect, visit);
+        }
+    }
+
+    /// Keeps the `k` nearest points seen so far in `nearest`, the furthest on top
+    fn collect_nearest<'a>(
+        &'a self,
+        node: usize,
+        pos: Vec2,
+        k: usize,
+        nearest: &mut BinaryHeap<Candidate<&'a T>>,
+    ) {
+        let furthest = |nearest: &BinaryHeap<Candidate<_>>| match nearest.peek() {
+            Some(Candidate(dist_sq, _)) if nearest.len() == k => *dist_sq,
+            _ => f32::INFINITY,
+        };
+
+        let Node { boundary, len, .. } = &self.nodes[node];
+
+        if *len == 0 || rect_distance_sq(pos, boundary) > furthest(nearest) {
+            return;
+        }
+
+        for (centre, _, data) in self.points_of(node) {
+            let dist_sq = pos.distance_squared(*centre);
+
+            if dist_sq < furthest(nearest) {
+                nearest.push(Candidate(dist_sq, data));
+
+                if nearest.len() > k {
+                    nearest.pop();
+                }
+            }
+        }
+
+        let children = self.children_of(node);
+        if children.is_empty() {
+            return;
+        }
+
+        // closer children first, so the further ones are more likely to be skipped
+        let mut order = [0, 1, 2, 3].map(|quadrant| children.start + quadrant);
+        order.sort_unstable_by(|a, b| {
+            rect_distance_sq(pos, &self.nodes[*a].boundary)
+                .total_cmp(&rect_distance_sq(pos, &self.nodes[*b].boundary))
+        });
+
+        for child in order {
+            self.collect_nearest(child, pos, k, nearest);
+        }
+    }
+
+    /// Aggregates mass and centre of mass of every node for `barnes_hut_acceleration`,
+    /// this has to be called again after pushing new points
+    pub fn compute_mass_distribution(&mut self, mass_of: &impl Fn(&T) -> f32) {
+        self.aggregate_mass(0, mass_of);
+    }
+
+    fn aggregate_mass(&mut self, node: usize, mass_of: &impl Fn(&T) -> f32) {
+        let mut mass = 0.0;
+        let mut moment = Vec2::ZERO;
+
+        for (pos, _, data) in self.points_of(node) {
+            let m = mass_of(data);
+            mass += m;
+            moment += *pos * m;
+        }
+
+        for child in self.children_of(node) {
+            self.aggregate_mass(child, mass_of);
+
+            let Node {
+                mass: child_mass,
+                centre_of_mass,
+                ..
+            } = self.nodes[child];
+
+            mass += child_mass;
+            moment += centre_of_mass * child_mass;
+        }
+
+        let node = &mut self.nodes[node];
+        node.mass = mass;
+        node.centre_of_mass = if mass > 0.0 {
+            moment / mass
+        } else {
+            Vec2::ZERO
+        };
+    }
+
+    /// Approximates `sum(m * r / |r|^3)` over every point as seen from `pos`, to be
+    /// scaled by the gravitational constant.
+    ///
+    /// Nodes whose size divided by their distance is below `theta` are treated as a
+    /// single body at their centre of mass, `theta = 0.0` gives the exact sum.
+    pub fn barnes_hut_acceleration(
+        &self,
+        pos: Vec2,
+        theta: f32,
+        softening: f32,
+        mass_of: &impl Fn(&T) -> f32,
+    ) -> Vec2 {
+        self.pull_of(0, pos, theta, softening, mass_of)
+    }
+
+    fn pull_of(
+        &self,
+        node: usize,
+        pos: Vec2,
+        theta: f32,
+        softening: f32,
+        mass_of: &impl Fn(&T) -> f32,
+    ) -> Vec2 {
+        let Node {
+            boundary,
+            mass,
+            centre_of_mass,
+            ..
+        } = &self.nodes[node];
+
+        if *mass == 0.0 {
+            return Vec2::ZERO;
+        }
+
+        let delta = *centre_of_mass - pos;
+        let size = boundary.width.max(boundary.height);
+
+        if size * size < theta * theta * delta.length_squared() {
+            return gravitational_pull(delta, *mass, softening);
+        }
+
+        let accel = self
+            .points_of(node)
+            .map(|(point, _, data)| gravitational_pull(*point - pos, mass_of(data), softening))
+            .sum::<Vec2>();
+
+        accel
+            + self
+                .children_of(node)
+                .map(|child| self.pull_of(child, pos, theta, softening, mass_of))
+                .sum::<Vec2>()
     }
 
     pub fn push(&mut self, point: (Vec2, f32, T)) {
-        if !inside_boundary(&self.boundary, &point.0) {
+        if !inside_boundary(&self.nodes[0].boundary, &point.0) {
             return;
         }
 
-        if self.points.len() < self.capacity {
-            self.points.push(Some(point));
+        self.insert(0, point);
+    }
+
+    /// Files `point` under `node` or one of its descendants, `node` has to
+    /// contain it
+    fn insert(&mut self, mut node: usize, point: (Vec2, f32, T)) {
+        let (pos, radius, _) = point;
+
+        loop {
+            let held = self.points[node].len();
+            let Node {
+                depth,
+                children,
+                len,
+                max_radius,
+                ..
+            } = &mut self.nodes[node];
+
+            *len += 1;
+            *max_radius = max_radius.max(radius);
+
+            if held < self.capacity || *depth == MAX_DEPTH {
+                break;
+            }
+
+            if *children == NONE {
+                self.split(node);
+            }
+
+            node = self.nodes[node].children + self.nodes[node].quadrant(pos);
+        }
+
+        self.points[node].push(point);
+    }
+
+    /// Gives `node` four empty children
+    fn split(&mut self, node: usize) {
+        let Node {
+            boundary, depth, ..
+        } = &self.nodes[node];
+
+        let children = quarters(boundary).map(|boundary| Node::new(boundary, depth + 1));
+
+        let first = match self.free_nodes.pop() {
+            Some(first) => {
+                self.nodes[first..first + 4].clone_from_slice(&children);
+                first
+            }
+
+            None => {
+                self.nodes.extend(children);
+
+                if self.points.len() < self.nodes.len() {
+                    self.points.resize_with(self.nodes.len(), Vec::new);
+                }
+
+                self.nodes.len() - 4
+            }
+        };
+
+        self.nodes[node].children = first;
+    }
+
+    /// Pulls the points of the descendants of `node` back into it once they all
+    /// fit, and shrinks `max_radius` to what's left
+    fn merge_if_sparse(&mut self, node: usize) {
+        let Node { children, len, .. } = self.nodes[node];
+
+        if children != NONE && len <= self.capacity {
+            for child in children..children + 4 {
+                self.absorb(node, child);
+            }
+
+            self.free_nodes.push(children);
+            self.nodes[node].children = NONE;
+        }
+
+        let max_radius = self
+            .points_of(node)
+            .map(|(_, radius, _)| *radius)
+            .chain(
+                self.children_of(node)
+                    .map(|child| self.nodes[child].max_radius),
+            )
+            .fold(0.0, f32::max);
+
+        self.nodes[node].max_radius = max_radius;
+    }
+
+    /// Moves every point of `from` and its descendants into `into`, freeing the
+    /// descendants
+    fn absorb(&mut self, into: usize, from: usize) {
+        // the emptied list goes back to keep its memory
+        let mut points = std::mem::take(&mut self.points[from]);
+        self.points[into].append(&mut points);
+        self.points[from] = points;
+
+        if let Some(children) = self.children_of(from).next() {
+            for child in children..children + 4 {
+                self.absorb(into, child);
+            }
+
+            self.free_nodes.push(children);
+        }
+    }
+}
+
+impl<T: Send> QuadTree<T> {
+    /// Like `clear` and pushing every point in order, which gives the same tree,
+    /// but the quarters of nodes with many points below them are built in parallel
+    pub fn par_rebuild(&mut self, boundary: Rect, mut points: Vec<(Vec2, f32, T)>) {
+        self.clear(boundary);
+
+        let boundary = &self.nodes[0].boundary;
+        points.retain(|(pos, ..)| inside_boundary(boundary, pos));
+
+        self.fill(&mut points);
+    }
+
+    /// Moves `points` under the root, which has to be empty and contain them
+    fn fill(&mut self, points: &mut Vec<(Vec2, f32, T)>) {
+        let Node {
+            boundary, depth, ..
+        } = self.nodes[0].clone();
+
+        if points.len() < PARALLEL_BUILD_MIN || points.len() <= self.capacity || depth == MAX_DEPTH
+        {
+            for point in points.drain(..) {
+                self.insert(0, point);
+            }
+
             return;
         }
 
-        // sub-divide into 4 parts
-
-        if self.children.is_none() {
-            let Rect {
-                left,
-                top,
-                width,
-                height,
-            } = self.boundary;
-
-            let hw = width * 0.5; // half width
-            let hh = height * 0.5; // half height
-
-            self.children = Some(Box::new([
-                // top left
-                QuadTree::new(
-                    self.capacity,
-                    Rect {
-                        left,
-                        top,
-                        width: hw,
-                        height: hh,
-                    },
-                ),
-                // top right
-                QuadTree::new(
-                    self.capacity,
-                    Rect {
-                        left: left + hw,
-                        top,
-                        width: hw,
-                        height: hh,
-                    },
-                ),
-                // bottom left
-                QuadTree::new(
-                    self.capacity,
-                    Rect {
-                        left,
-                        top: top + hh,
-                        width: hw,
-                        height: hh,
-                    },
-                ),
-                // bottom right
-                QuadTree::new(
-                    self.capacity,
-                    Rect {
-                        left: left + hw,
-                        top: top + hh,
-                        width: hw,
-                        height: hh,
-                    },
-                ),
-            ]));
-        }
-
-        self.children
-            .as_mut()
-            .unwrap()
-            .iter_mut()
-            .for_each(|c| c.push(point.clone()));
+        let capacity = self.capacity;
+        let mut scratch = std::mem::take(&mut self.scratch);
+
+        if scratch.is_empty() {
+            scratch = (0..4)
+                .map(|_| (QuadTree::new(capacity, Rect::default()), vec![]))
+                .collect();
+        }
+
+        // as when pushing them one by one, the first points stay in the root and
+        // the rest go to the quarters in the same order
+        let mut rest = points.drain(..);
+        self.points[0].extend(rest.by_ref().take(capacity));
+
+        for point in rest {
+            scratch[self.nodes[0].quadrant(point.0)].1.push(point);
+        }
+
+        scratch.par_iter_mut().zip(quarters(&boundary)).for_each(
+            |((subtree, points), boundary)| {
+                subtree.capacity = capacity;
+                subtree.clear(boundary);
+                subtree.nodes[0].depth = depth + 1;
+                subtree.fill(points);
+            },
+        );
+
+        let root = &mut self.nodes[0];
+        root.len = self.points[0].len()
+            + scratch
+                .iter()
+                .map(|(subtree, _)| subtree.len())
+                .sum::<usize>();
+        root.max_radius = self.points[0]
+            .iter()
+            .map(|(_, radius, _)| *radius)
+            .chain(
+                scratch
+                    .iter()
+                    .map(|(subtree, _)| subtree.nodes[0].max_radius),
+            )
+            .fold(0.0, f32::max);
+
+        self.graft(&mut scratch);
+        self.scratch = scratch;
+    }
+
+    /// Makes the roots of the four `scratch` trees the children of the root, copying
+    /// their nodes over and swapping their points for the root's emptied lists
+    fn graft(&mut self, scratch: &mut [Scratch<T>]) {
+        let first = self.nodes.len();
+
+        // the roots come first, the other nodes of each subtree follow in order
+        let mut offsets = [0; 4];
+        let mut end = first + 4;
+        for (offset, (subtree, _)) in offsets.iter_mut().zip(scratch.iter()) {
+            *offset = end - 1;
+            end += subtree.nodes.len() - 1;
+        }
+
+        let moved = |quadrant: usize, index: usize| match index {
+            0 => first + quadrant,
+            NONE => NONE,
+            index => offsets[quadrant] + index,
+        };
+
+        self.nodes.reserve(end - first);
+        for (quadrant, (subtree, _)) in scratch.iter().enumerate() {
+            self.nodes.push(subtree.nodes[0].clone());
+            self.nodes[first + quadrant].children = moved(quadrant, subtree.nodes[0].children);
+        }
+
+        for (quadrant, (subtree, _)) in scratch.iter().enumerate() {
+            for node in &subtree.nodes[1..] {
+                let children = moved(quadrant, node.children);
+                self.nodes.push(Node {
+                    children,
+                    ..node.clone()
+                });
+            }
+        }
+
+        if self.points.len() < end {
+            self.points.resize_with(end, Vec::new);
+        }
+
+        // the lists the subtrees get back are empty since the root was cleared
+        for (quadrant, (subtree, _)) in scratch.iter_mut().enumerate() {
+            let nodes = subtree.nodes.len();
+
+            for (index, points) in subtree.points[..nodes].iter_mut().enumerate() {
+                std::mem::swap(&mut self.points[moved(quadrant, index)], points);
+            }
+        }
+
+        self.nodes[0].children = first;
     }
 }
 
+impl<T: Clone> QuadTree<T> {
+    /// Get all the points whose circle overlaps the specified one
+    pub fn query(&self, circle_centre: Vec2, circle_radius: f32) -> Vec<T> {
+        let mut found = vec![];
+        self.for_each_in_circle(circle_centre, circle_radius, &mut |_, _, data| {
+            found.push(data.clone())
+        });
+
+        found
+    }
+
+    /// Get all the points whose circle overlaps `rect`, edges included
+    pub fn query_rect(&self, rect: &Rect) -> Vec<T> {
+        let mut found = vec![];
+        self.for_each_in_rect(rect, &mut |_, _, data| found.push(data.clone()));
+
+        found
+    }
+
+    /// The point whose centre is closest to `pos`
+    pub fn nearest(&self, pos: Vec2) -> Option<T> {
+        self.k_nearest(pos, 1).pop()
+    }
+
+    /// The `k` points whose centres are closest to `pos`, nearest first. Points at
+    /// the same distance are in no particular order.
+    pub fn k_nearest(&self, pos: Vec2, k: usize) -> Vec<T> {
+        if k == 0 {
+            return vec![];
+        }
+
+        let mut nearest = BinaryHeap::with_capacity(k + 1);
+        self.collect_nearest(0, pos, k, &mut nearest);
+
+        nearest
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Candidate(_, data)| data.clone())
+            .collect()
+    }
+}
+
+impl<T: PartialEq> QuadTree<T> {
+    /// Takes out the point at `pos` carrying `data`, merging nodes that become
+    /// sparse on the way. A point is found by the exact position it was last given.
+    pub fn remove(&mut self, pos: Vec2, data: &T) -> Option<(Vec2, f32, T)> {
+        if !inside_boundary(&self.nodes[0].boundary, &pos) {
+            return None;
+        }
+
+        self.remove_from(0, pos, data)
+    }
+
+    fn remove_from(&mut self, node: usize, pos: Vec2, data: &T) -> Option<(Vec2, f32, T)> {
+        let point = match self.slot_of(node, pos, data) {
+            Some(slot) => self.points[node].swap_remove(slot),
+            None if self.nodes[node].children == NONE => return None,
+            None => {
+                let child = self.nodes[node].children + self.nodes[node].quadrant(pos);
+                self.remove_from(child, pos, data)?
+            }
+        };
+
+        self.nodes[node].len -= 1;
+        self.merge_if_sparse(node);
+
+        Some(point)
+    }
+
+    /// Moves the point at `pos` carrying `data` to `new_pos`, it's moved up the
+    /// tree only as far as the first node that contains it again. Returns `false`
+    /// if there's no such point or it left the boundary of the tree, which
+    /// removes it.
+    pub fn update(&mut self, pos: Vec2, data: &T, new_pos: Vec2) -> bool {
+        if !inside_boundary(&self.nodes[0].boundary, &pos) {
+            return false;
+        }
+
+        matches!(self.relocate(0, pos, data, new_pos), Relocation::Kept)
+    }
+
+    fn relocate(&mut self, node: usize, pos: Vec2, data: &T, new_pos: Vec2) -> Relocation<T> {
+        let inside = inside_boundary(&self.nodes[node].boundary, &new_pos);
+
+        let mut point = match self.slot_of(node, pos, data) {
+            // a node can hold anything inside its boundary, even with children
+            Some(slot) if inside => {
+                self.points[node][slot].0 = new_pos;
+                return Relocation::Kept;
+            }
+
+            Some(slot) => self.points[node].swap_remove(slot),
+            None if self.nodes[node].children == NONE => return Relocation::Missing,
+            None => {
+                let child = self.nodes[node].children + self.nodes[node].quadrant(pos);
+
+                match self.relocate(child, pos, data, new_pos) {
+                    Relocation::Escaped(point) => point,
+                    relocation => return relocation,
+                }
+            }
+        };
+
+        self.nodes[node].len -= 1;
+        self.merge_if_sparse(node);
+        point.0 = new_pos;
+
+        if !inside {
+            return Relocation::Escaped(point);
+        }
+
+        self.insert(node, point);
+        Relocation::Kept
+    }
+
+    /// Index of the point at `pos` carrying `data` among the ones held by `node`
+    fn slot_of(&self, node: usize, pos: Vec2, data: &T) -> Option<usize> {
+        self.points[node]
+            .iter()
+            .position(|(point_pos, _, point_data)| *point_pos == pos && point_data == data)
+    }
+}
+
+/// A point found by `QuadTree::k_nearest` and its squared distance,
+/// ordered by the distance alone
+struct Candidate<T>(f32, T);
+
+impl<T> PartialEq for Candidate<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Candidate<T> {}
+
+impl<T> PartialOrd for Candidate<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Candidate<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Where `QuadTree::relocate` left a point
+enum Relocation<T> {
+    Missing,
+    Kept,
+
+    /// Moved out of the node, to be inserted again by one that contains it
+    Escaped((Vec2, f32, T)),
+}
+
 fn inside_boundary(boundary: &Rect, point: &Vec2) -> bool {
     let Rect {
         left,
@@ -184,7 +794,68 @@ fn inside_boundary(boundary: &Rect, point: &Vec2) -> bool {
     (left <= point.x && point.x < left + width) && (top <= point.y && point.y < top + height)
 }
 
-/// Checks circle-rectangle intersection
+/// The four quarters of `rect`, in the order children are stored
+fn quarters(rect: &Rect) -> [Rect; 4] {
+    let Rect {
+        left,
+        top,
+        width,
+        height,
+    } = *rect;
+
+    let hw = width * 0.5; // half width
+    let hh = height * 0.5; // half height
+
+    // the far halves end exactly where this node does, rounding can't leave
+    // a point of the node outside of all its children
+    let (right, bottom) = (left + width, top + height);
+    let (mid_x, mid_y) = (left + hw, top + hh);
+
+    [
+        // top left
+        Rect {
+            left,
+            top,
+            width: hw,
+            height: hh,
+        },
+        // top right
+        Rect {
+            left: mid_x,
+            top,
+            width: right - mid_x,
+            height: hh,
+        },
+        // bottom left
+        Rect {
+            left,
+            top: mid_y,
+            width: hw,
+            height: bottom - mid_y,
+        },
+        // bottom right
+        Rect {
+            left: mid_x,
+            top: mid_y,
+            width: right - mid_x,
+            height: bottom - mid_y,
+        },
+    ]
+}
+
+/// Softened inverse square pull towards a mass at offset `delta`,
+/// zero for the point itself
+fn gravitational_pull(delta: Vec2, mass: f32, softening: f32) -> Vec2 {
+    let dist_sq = delta.length_squared() + softening * softening;
+
+    if dist_sq == 0.0 {
+        return Vec2::ZERO;
+    }
+
+    delta * (mass / (dist_sq * dist_sq.sqrt()))
+}
+
+/// Checks circle-rectangle intersection, touching counts like in `cc_intersection`
 fn cr_intersection(circle_centre: Vec2, circle_radius: f32, rect: &Rect) -> bool {
     let closest_x = rect.left.max(circle_centre.x.min(rect.left + rect.width));
     let closest_y = rect.top.max(circle_centre.y.min(rect.top + rect.height));
@@ -193,11 +864,28 @@ fn cr_intersection(circle_centre: Vec2, circle_radius: f32, rect: &Rect) -> bool
     let dy = circle_centre.y - closest_y;
 
     let dist_sq = dx * dx + dy * dy;
-    dist_sq < (circle_radius * circle_radius)
+    dist_sq <= (circle_radius * circle_radius)
+}
+
+/// Squared distance from `pos` to the closest point of `rect`, zero inside it
+fn rect_distance_sq(pos: Vec2, rect: &Rect) -> f32 {
+    let closest = pos.clamp(
+        glam::vec2(rect.left, rect.top),
+        glam::vec2(rect.left + rect.width, rect.top + rect.height),
+    );
+
+    pos.distance_squared(closest)
+}
+
+/// Checks rectangle-rectangle intersection, touching edges count
+fn rr_intersection(a: &Rect, b: &Rect) -> bool {
+    a.left <= b.left + b.width
+        && b.left <= a.left + a.width
+        && a.top <= b.top + b.height
+        && b.top <= a.top + a.height
 }
 
 /// Checks circle-circle intersection
 pub fn cc_intersection(c1: Vec2, r1: f32, c2: Vec2, r2: f32) -> bool {
     (c1 - c2).length_squared() <= (r1 + r2).powi(2)
 }
-
@@ -0,0 +1,131 @@
+use glow::HasContext;
+
+use particle_simulator::FLOATS_PER_INSTANCE;
+
+pub const BUFFER_ACCESS_FLAGS: u32 =
+    glow::MAP_WRITE_BIT | glow::MAP_READ_BIT | glow::MAP_PERSISTENT_BIT | glow::MAP_COHERENT_BIT;
+
+pub const INSTANCE_DATA_STRIDE: usize = std::mem::size_of::<f32>() * FLOATS_PER_INSTANCE;
+
+const LINE_VERTEX_STRIDE: usize = std::mem::size_of::<f32>() * 2;
+
+pub unsafe fn reallocate_instance_vbo(
+    gl: &glow::Context,
+    buffer_capacity: usize,
+    old_capacity: usize,
+    instance_data_ptr: &mut *mut f32,
+    instance_vbo: &mut glow::NativeBuffer,
+    vao: glow::NativeVertexArray,
+) {
+    let new_vbo = gl.create_buffer().unwrap();
+
+    gl.bind_buffer(glow::ARRAY_BUFFER, Some(new_vbo));
+    gl.buffer_storage(
+        glow::ARRAY_BUFFER,
+        (buffer_capacity * INSTANCE_DATA_STRIDE) as _,
+        None,
+        BUFFER_ACCESS_FLAGS,
+    );
+
+    let new_ptr = gl.map_buffer_range(
+        glow::ARRAY_BUFFER,
+        0,
+        (buffer_capacity * INSTANCE_DATA_STRIDE) as _,
+        BUFFER_ACCESS_FLAGS,
+    ) as *mut f32;
+
+    std::ptr::copy_nonoverlapping(
+        *instance_data_ptr,
+        new_ptr,
+        old_capacity * FLOATS_PER_INSTANCE,
+    );
+
+    gl.bind_vertex_array(Some(vao));
+
+    gl.bind_buffer(glow::ARRAY_BUFFER, Some(*instance_vbo));
+    gl.unmap_buffer(glow::ARRAY_BUFFER);
+    gl.delete_buffer(*instance_vbo);
+
+    gl.bind_buffer(glow::ARRAY_BUFFER, Some(new_vbo));
+
+    setup_instance_attributes(gl);
+
+    *instance_data_ptr = new_ptr;
+    *instance_vbo = new_vbo;
+
+    // unbind
+    // gl.bind_vertex_array(None);
+    // gl.bind_buffer(glow::ARRAY_BUFFER, None);
+}
+
+pub unsafe fn setup_instance_attributes(gl: &glow::Context) {
+    // position
+    gl.enable_vertex_attrib_array(1);
+    gl.vertex_attrib_divisor(1, 1);
+    gl.vertex_attrib_pointer_f32(1, 2, glow::FLOAT, false, INSTANCE_DATA_STRIDE as _, 0);
+
+    // radius
+    gl.enable_vertex_attrib_array(2);
+    gl.vertex_attrib_divisor(2, 1);
+    gl.vertex_attrib_pointer_f32(
+        2,
+        1,
+        glow::FLOAT,
+        false,
+        INSTANCE_DATA_STRIDE as _,
+        (std::mem::size_of::<f32>() * 2) as _,
+    );
+
+    // color
+    gl.enable_vertex_attrib_array(3);
+    gl.vertex_attrib_divisor(3, 1);
+    gl.vertex_attrib_pointer_f32(
+        3,
+        3,
+        glow::FLOAT,
+        false,
+        INSTANCE_DATA_STRIDE as _,
+        (std::mem::size_of::<f32>() * 3) as _,
+    );
+}
+
+/// Vertex array for collider outlines, drawn as `glow::LINES`
+pub unsafe fn create_line_vao(gl: &glow::Context) -> (glow::NativeVertexArray, glow::NativeBuffer) {
+    let vao = gl.create_vertex_array().unwrap();
+    let vbo = gl.create_buffer().unwrap();
+
+    gl.bind_vertex_array(Some(vao));
+    gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo));
+
+    gl.enable_vertex_attrib_array(0);
+    gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, LINE_VERTEX_STRIDE as _, 0);
+
+    gl.bind_vertex_array(None);
+
+    (vao, vbo)
+}
+
+/// Replaces the contents of a buffer made by `create_line_vao`, returns the vertex count
+pub unsafe fn upload_lines(
+    gl: &glow::Context,
+    vbo: glow::NativeBuffer,
+    lines: &[[glam::Vec2; 2]],
+) -> usize {
+    let vertices = lines
+        .iter()
+        .flatten()
+        .flat_map(|vertex| vertex.to_array())
+        .collect::<Vec<f32>>();
+
+    gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo));
+    gl.buffer_data_u8_slice(
+        glow::ARRAY_BUFFER,
+        std::slice::from_raw_parts(
+            vertices.as_ptr() as _,
+            vertices.len() * std::mem::size_of::<f32>(),
+        ),
+        glow::DYNAMIC_DRAW,
+    );
+
+    lines.len() * 2
+}
@@ -0,0 +1,1151 @@
+use rand::Rng;
+use serde::Deserialize;
+use toml::Spanned;
+
+use super::*;
+use boundary::Boundary;
+use colliders::ColliderShape;
+use emitter::{Distribution, EmissionMode, SpawnShape, ValueRange};
+use forces::ForceField;
+
+/// Initial conditions of a simulation, written in TOML (see `scenes/` for examples).
+///
+/// Every entry keeps its location in the file so [`Scene::validate`] can point at
+/// the offending line.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scene {
+    /// Size of the simulated domain, the window starts out this big too
+    pub size: Spanned<[i32; 2]>,
+
+    /// Reflecting on every edge when left out
+    pub boundary: Option<Spanned<BoundaryDesc>>,
+
+    /// Random when left out
+    pub seed: Option<u64>,
+
+    pub integrator: Option<Spanned<String>>,
+    pub broad_phase: Option<Spanned<String>>,
+    pub quad_capacity: Option<Spanned<usize>>,
+    pub contact_passes: Option<Spanned<usize>>,
+    pub gravitation: Option<Spanned<GravitationDesc>>,
+
+    #[serde(default)]
+    pub force_fields: Vec<Spanned<ForceFieldDesc>>,
+
+    #[serde(default)]
+    pub materials: Vec<Spanned<MaterialDesc>>,
+
+    #[serde(default)]
+    pub particles: Vec<Spanned<ParticleDesc>>,
+
+    #[serde(default)]
+    pub lattices: Vec<Spanned<LatticeDesc>>,
+
+    #[serde(default)]
+    pub emitters: Vec<Spanned<EmitterDesc>>,
+
+    #[serde(default)]
+    pub colliders: Vec<Spanned<ColliderDesc>>,
+}
+
+/// Edges of the domain, each one of `reflect`, `wrap`, `absorb` or `open`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoundaryDesc {
+    /// Used for the edges that aren't given
+    #[serde(default = "reflect")]
+    pub all: String,
+
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub top: Option<String>,
+    pub bottom: Option<String>,
+
+    /// Of the reflecting edges
+    #[serde(default = "one")]
+    pub restitution: f32,
+}
+
+impl BoundaryDesc {
+    /// Left, right, top and bottom
+    fn boundaries(&self) -> Result<[Boundary; 4], String> {
+        let mut boundaries = [&self.left, &self.right, &self.top, &self.bottom]
+            .map(|edge| edge.as_deref().unwrap_or(&self.all).parse::<Boundary>());
+
+        for boundary in boundaries.iter_mut().flatten() {
+            if let Boundary::Reflect { restitution } = boundary {
+                *restitution = self.restitution;
+            }
+        }
+
+        let [left, right, top, bottom] = boundaries;
+        Ok([left?, right?, top?, bottom?])
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GravitationDesc {
+    pub constant: f32,
+    pub theta: Option<f32>,
+    pub softening: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForceFieldDesc {
+    Gravity([f32; 2]),
+    LinearDrag(f32),
+    QuadraticDrag(f32),
+    Wind { velocity: [f32; 2], drag: f32 },
+}
+
+/// Particles, lattices and emitters refer to materials by `name`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialDesc {
+    /// Naming a material `default` replaces the one used when none is given
+    pub name: String,
+
+    #[serde(default = "one")]
+    pub density: f32,
+
+    /// Perfectly elastic and frictionless by default
+    #[serde(default = "one")]
+    pub restitution: f32,
+
+    #[serde(default)]
+    pub friction: f32,
+
+    #[serde(default = "white_palette")]
+    pub palette: Vec<[f32; 3]>,
+
+    #[serde(default)]
+    pub cohesion: f32,
+}
+
+impl MaterialDesc {
+    fn material(&self) -> Material {
+        Material {
+            density: self.density,
+            surface: Surface {
+                restitution: self.restitution,
+                friction: self.friction,
+            },
+            palette: self.palette.clone(),
+            cohesion: self.cohesion,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParticleDesc {
+    pub position: [f32; 2],
+
+    #[serde(default)]
+    pub velocity: [f32; 2],
+
+    pub radius: Spanned<f32>,
+
+    /// Name of the material, the default one when left out
+    pub material: Option<Spanned<String>>,
+
+    /// Defaults to the material's density times the area
+    pub mass: Option<Spanned<f32>>,
+
+    /// Picked from the material's palette when left out
+    pub color: Option<Spanned<[f32; 3]>>,
+
+    /// Seconds until the particle fades out, forever when left out
+    pub lifetime: Option<Spanned<f32>>,
+
+    /// Color the particle fades to over its lifetime, black when left out
+    pub fade_color: Option<Spanned<[f32; 3]>>,
+}
+
+/// A grid of particles that only differ in color when it comes from a palette
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LatticeDesc {
+    /// Centre of the first particle
+    pub origin: [f32; 2],
+    pub columns: Spanned<usize>,
+    pub rows: Spanned<usize>,
+
+    /// Distance between neighbouring centres
+    pub spacing: Spanned<[f32; 2]>,
+
+    #[serde(default)]
+    pub velocity: [f32; 2],
+
+    /// Random offset of up to this much added to each velocity component, none when
+    /// left out
+    pub velocity_jitter: Option<Spanned<f32>>,
+
+    /// Drawn for every particle when it's a range or a distribution
+    pub radius: Spanned<RadiusDesc>,
+
+    pub material: Option<Spanned<String>>,
+    pub mass: Option<Spanned<f32>>,
+    pub color: Option<Spanned<[f32; 3]>>,
+    pub lifetime: Option<Spanned<f32>>,
+    pub fade_color: Option<Spanned<[f32; 3]>>,
+}
+
+/// Looks of a particle, shared by single particles and lattices
+#[derive(Clone, Copy, Debug)]
+struct ParticleLook<'a> {
+    radius: f32,
+    material: Option<&'a Spanned<String>>,
+    mass: Option<&'a Spanned<f32>>,
+    color: Option<&'a Spanned<[f32; 3]>>,
+    lifetime: Option<&'a Spanned<f32>>,
+    fade_color: Option<&'a Spanned<[f32; 3]>>,
+}
+
+impl ParticleDesc {
+    fn look(&self) -> ParticleLook<'_> {
+        ParticleLook {
+            radius: *self.radius.get_ref(),
+            material: self.material.as_ref(),
+            mass: self.mass.as_ref(),
+            color: self.color.as_ref(),
+            lifetime: self.lifetime.as_ref(),
+            fade_color: self.fade_color.as_ref(),
+        }
+    }
+}
+
+impl LatticeDesc {
+    fn look(&self, radius: f32) -> ParticleLook<'_> {
+        ParticleLook {
+            radius,
+            material: self.material.as_ref(),
+            mass: self.mass.as_ref(),
+            color: self.color.as_ref(),
+            lifetime: self.lifetime.as_ref(),
+            fade_color: self.fade_color.as_ref(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmitterDesc {
+    pub position: [f32; 2],
+
+    /// Particles per second, exclusive with `burst`
+    pub rate: Option<Spanned<f32>>,
+    pub burst: Option<Spanned<BurstDesc>>,
+
+    #[serde(default = "point")]
+    pub shape: ShapeDesc,
+
+    /// Degrees, 0 points to the right and 90 down
+    #[serde(default)]
+    pub direction: f32,
+
+    /// Full opening angle of the velocity cone in degrees
+    #[serde(default = "full_circle")]
+    pub spread: f32,
+
+    pub speed: Spanned<RangeDesc>,
+    pub radius: Spanned<RadiusDesc>,
+
+    /// Name of the material of the particles, the default one when left out
+    pub material: Option<Spanned<String>>,
+
+    /// Derived from the radius and the material's density when left out
+    pub mass: Option<Spanned<RangeDesc>>,
+
+    /// Picked from the material's palette when left out
+    pub color: Option<Spanned<[RangeDesc; 3]>>,
+
+    pub lifetime: Option<Spanned<f32>>,
+
+    /// Color the particles fade to over their lifetime, black when left out
+    pub fade_color: Option<Spanned<[f32; 3]>>,
+}
+
+/// Static geometry, exactly one of the shapes has to be given
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColliderDesc {
+    /// `[[x, y], [x, y]]`
+    pub segment: Option<[[f32; 2]; 2]>,
+    pub aabb: Option<AabbDesc>,
+    pub circle: Option<CircleDesc>,
+
+    /// Vertices of a convex polygon
+    pub polygon: Option<Vec<[f32; 2]>>,
+
+    #[serde(default = "one")]
+    pub restitution: f32,
+
+    #[serde(default)]
+    pub friction: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AabbDesc {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircleDesc {
+    pub centre: [f32; 2],
+    pub radius: f32,
+}
+
+impl ColliderDesc {
+    /// `None` unless exactly one shape is given
+    fn shape(&self) -> Option<ColliderShape> {
+        let shapes = [
+            self.segment.map(|[a, b]| ColliderShape::Segment {
+                a: a.into(),
+                b: b.into(),
+            }),
+            self.aabb.map(|aabb| ColliderShape::Aabb {
+                min: aabb.min.into(),
+                max: aabb.max.into(),
+            }),
+            self.circle.map(|circle| ColliderShape::Circle {
+                centre: circle.centre.into(),
+                radius: circle.radius,
+            }),
+            self.polygon.as_ref().map(|vertices| {
+                ColliderShape::Polygon(vertices.iter().map(|v| Vec2::from(*v)).collect())
+            }),
+        ];
+
+        let mut shapes = shapes.into_iter().flatten();
+        let shape = shapes.next()?;
+
+        shapes.next().is_none().then_some(shape)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BurstDesc {
+    pub count: usize,
+
+    /// Seconds between bursts, a single burst when left out
+    pub interval: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShapeDesc {
+    Point,
+    Circle(f32),
+    Line([f32; 2]),
+    Rectangle([f32; 2]),
+}
+
+/// Either a single value or `[min, max]`
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RangeDesc {
+    Constant(f32),
+    Range([f32; 2]),
+}
+
+impl RangeDesc {
+    fn to_range(self) -> ValueRange {
+        match self {
+            Self::Constant(value) => ValueRange::constant(value),
+            Self::Range([min, max]) => ValueRange::new(min, max),
+        }
+    }
+}
+
+/// A single value, `[min, max]` or a table naming one of the distributions, e.g.
+/// `{ normal = { mean = 5, std_dev = 1 } }`
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RadiusDesc {
+    Constant(f32),
+    Range([f32; 2]),
+    Distribution(DistributionDesc),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum DistributionDesc {
+    /// Truncated to `range`, three standard deviations around the mean when left out
+    Normal {
+        mean: f32,
+        std_dev: f32,
+        range: Option<[f32; 2]>,
+    },
+
+    LogNormal {
+        median: f32,
+        sigma: f32,
+    },
+
+    /// Density proportional to `radius^-exponent`
+    PowerLaw {
+        range: [f32; 2],
+        exponent: f32,
+    },
+}
+
+impl RadiusDesc {
+    fn to_distribution(self) -> Distribution {
+        match self {
+            Self::Constant(value) => Distribution::constant(value),
+            Self::Range([min, max]) => Distribution::Uniform(ValueRange::new(min, max)),
+
+            Self::Distribution(DistributionDesc::Normal {
+                mean,
+                std_dev,
+                range,
+            }) => {
+                let [min, max] = range.unwrap_or([mean - 3.0 * std_dev, mean + 3.0 * std_dev]);
+
+                Distribution::Normal {
+                    mean,
+                    std_dev,
+                    range: ValueRange::new(min, max),
+                }
+            }
+
+            Self::Distribution(DistributionDesc::LogNormal { median, sigma }) => {
+                Distribution::LogNormal { median, sigma }
+            }
+
+            Self::Distribution(DistributionDesc::PowerLaw {
+                range: [min, max],
+                exponent,
+            }) => Distribution::PowerLaw {
+                range: ValueRange::new(min, max),
+                exponent,
+            },
+        }
+    }
+
+    /// Whether every value it can produce is positive
+    fn is_valid(self) -> bool {
+        let ascending = |range: ValueRange| range.min > 0.0 && range.min <= range.max;
+
+        match self.to_distribution() {
+            Distribution::Uniform(range) => ascending(range),
+            Distribution::Normal { std_dev, range, .. } => std_dev >= 0.0 && ascending(range),
+            Distribution::LogNormal { median, sigma } => median > 0.0 && sigma >= 0.0,
+            Distribution::PowerLaw { range, exponent } => ascending(range) && exponent.is_finite(),
+        }
+    }
+}
+
+fn reflect() -> String {
+    "reflect".to_string()
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn white_palette() -> Vec<[f32; 3]> {
+    vec![[1.0; 3]]
+}
+
+fn point() -> ShapeDesc {
+    ShapeDesc::Point
+}
+
+fn full_circle() -> f32 {
+    360.0
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Every problem found in a scene file
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneErrors(pub Vec<SceneError>);
+
+impl std::fmt::Display for SceneErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{error}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for SceneErrors {}
+
+impl Scene {
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&contents)?)
+    }
+
+    /// Parses and validates a scene
+    pub fn parse(source: &str) -> Result<Self, SceneErrors> {
+        let scene = toml::from_str::<Self>(source).map_err(|e| {
+            SceneErrors(vec![SceneError {
+                line: e
+                    .span()
+                    .map(|span| line_of(source, span.start))
+                    .unwrap_or(1),
+                message: e.message().to_string(),
+            }])
+        })?;
+
+        scene.validate(source)?;
+        Ok(scene)
+    }
+
+    /// Checks the values that parse fine but make no sense, `source` is the
+    /// text the scene was parsed from and is only used to find line numbers
+    pub fn validate(&self, source: &str) -> Result<(), SceneErrors> {
+        let mut errors = vec![];
+        let mut check = |ok: bool, start: usize, message: &str| {
+            if !ok {
+                errors.push(SceneError {
+                    line: line_of(source, start),
+                    message: message.to_string(),
+                });
+            }
+        };
+
+        let [width, height] = *self.size.get_ref();
+        check(
+            width > 0 && height > 0,
+            self.size.span().start,
+            "`size` must be positive",
+        );
+
+        if let Some(boundary) = &self.boundary {
+            let start = boundary.span().start;
+            let boundary = boundary.get_ref();
+
+            match boundary.boundaries() {
+                Ok([left, right, top, bottom]) => {
+                    let wrap = |boundary| boundary == Boundary::Wrap;
+
+                    check(
+                        wrap(left) == wrap(right) && wrap(top) == wrap(bottom),
+                        start,
+                        "opposite edges have to wrap together",
+                    );
+                }
+
+                Err(e) => check(false, start, &e),
+            }
+
+            check(
+                (0.0..=1.0).contains(&boundary.restitution),
+                start,
+                "`restitution` must be between 0 and 1",
+            );
+        }
+
+        if let Some(integrator) = &self.integrator {
+            if let Err(e) = integrator.get_ref().parse::<Integrator>() {
+                check(false, integrator.span().start, &e);
+            }
+        }
+
+        if let Some(broad_phase) = &self.broad_phase {
+            if let Err(e) = broad_phase.get_ref().parse::<BroadPhaseKind>() {
+                check(false, broad_phase.span().start, &e);
+            }
+        }
+
+        if let Some(capacity) = &self.quad_capacity {
+            check(
+                *capacity.get_ref() > 0,
+                capacity.span().start,
+                "`quad_capacity` must be at least 1",
+            );
+        }
+
+        if let Some(passes) = &self.contact_passes {
+            check(
+                *passes.get_ref() > 0,
+                passes.span().start,
+                "`contact_passes` must be at least 1",
+            );
+        }
+
+        if let Some(gravitation) = &self.gravitation {
+            let start = gravitation.span().start;
+            let gravitation = gravitation.get_ref();
+
+            check(
+                gravitation.theta.is_none_or(|theta| theta >= 0.0),
+                start,
+                "`theta` can't be negative",
+            );
+
+            check(
+                gravitation
+                    .softening
+                    .is_none_or(|softening| softening >= 0.0),
+                start,
+                "`softening` can't be negative",
+            );
+        }
+
+        for field in &self.force_fields {
+            let coefficient = match *field.get_ref() {
+                ForceFieldDesc::Gravity(_) => 0.0,
+                ForceFieldDesc::LinearDrag(k) | ForceFieldDesc::QuadraticDrag(k) => k,
+                ForceFieldDesc::Wind { drag, .. } => drag,
+            };
+
+            check(
+                coefficient >= 0.0,
+                field.span().start,
+                "drag coefficients can't be negative",
+            );
+        }
+
+        let check_surface = |check: &mut dyn FnMut(bool, usize, &str), surface: Surface, start| {
+            check(
+                (0.0..=1.0).contains(&surface.restitution),
+                start,
+                "`restitution` must be between 0 and 1",
+            );
+
+            check(
+                surface.friction >= 0.0,
+                start,
+                "`friction` can't be negative",
+            );
+        };
+
+        let mut names = vec!["default"];
+
+        for material in &self.materials {
+            let start = material.span().start;
+            let material = material.get_ref();
+
+            check(
+                !names[1..].contains(&material.name.as_str()),
+                start,
+                &format!("material `{}` is defined twice", material.name),
+            );
+
+            names.push(&material.name);
+
+            check(material.density > 0.0, start, "`density` must be positive");
+            check_surface(&mut check, material.material().surface, start);
+
+            check(
+                !material.palette.is_empty(),
+                start,
+                "`palette` needs at least one color",
+            );
+
+            check(
+                material
+                    .palette
+                    .iter()
+                    .flatten()
+                    .all(|c| (0.0..=1.0).contains(c)),
+                start,
+                "`palette` color components must be between 0 and 1",
+            );
+
+            check(
+                material.cohesion >= 0.0,
+                start,
+                "`cohesion` can't be negative",
+            );
+        }
+
+        let known_material = |material: &String| names.contains(&material.as_str());
+        let valid_color = |color: &[f32; 3]| color.iter().all(|c| (0.0..=1.0).contains(c));
+
+        let check_look = |check: &mut dyn FnMut(bool, usize, &str), look: ParticleLook| {
+            check_value(
+                check,
+                look.material,
+                known_material,
+                "`material` isn't defined in `materials`",
+            );
+
+            check_value(
+                check,
+                look.mass,
+                |mass| *mass > 0.0,
+                "`mass` must be positive",
+            );
+
+            check_value(
+                check,
+                look.color,
+                valid_color,
+                "`color` components must be between 0 and 1",
+            );
+
+            check_value(
+                check,
+                look.lifetime,
+                |lifetime| *lifetime > 0.0,
+                "`lifetime` must be positive",
+            );
+
+            check_value(
+                check,
+                look.fade_color,
+                valid_color,
+                "`fade_color` components must be between 0 and 1",
+            );
+        };
+
+        let radius_message = "`radius` must be a positive value, an ascending [min, max] \
+            or a distribution of positive values";
+
+        for particle in &self.particles {
+            let particle = particle.get_ref();
+
+            check_value(
+                &mut check,
+                Some(&particle.radius),
+                |radius| *radius > 0.0,
+                "`radius` must be positive",
+            );
+
+            check_look(&mut check, particle.look());
+        }
+
+        for lattice in &self.lattices {
+            let lattice = lattice.get_ref();
+
+            check_value(
+                &mut check,
+                Some(&lattice.radius),
+                |radius| radius.is_valid(),
+                radius_message,
+            );
+
+            // the radius was checked above
+            check_look(&mut check, lattice.look(0.0));
+
+            for count in [&lattice.columns, &lattice.rows] {
+                check_value(
+                    &mut check,
+                    Some(count),
+                    |count| *count > 0,
+                    "`columns` and `rows` must be at least 1",
+                );
+            }
+
+            check_value(
+                &mut check,
+                Some(&lattice.spacing),
+                |spacing| spacing.iter().all(|s| *s > 0.0),
+                "`spacing` must be positive",
+            );
+
+            check_value(
+                &mut check,
+                lattice.velocity_jitter.as_ref(),
+                |jitter| *jitter >= 0.0,
+                "`velocity_jitter` can't be negative",
+            );
+        }
+
+        for emitter in &self.emitters {
+            let start = emitter.span().start;
+            let emitter = emitter.get_ref();
+
+            match (&emitter.rate, &emitter.burst) {
+                (Some(rate), None) => check_value(
+                    &mut check,
+                    Some(rate),
+                    |rate| *rate >= 0.0,
+                    "`rate` can't be negative",
+                ),
+
+                (None, Some(burst)) => check_value(
+                    &mut check,
+                    Some(burst),
+                    |burst| burst.interval.is_none_or(|interval| interval > 0.0),
+                    "burst `interval` must be positive",
+                ),
+
+                _ => check(
+                    false,
+                    start,
+                    "emitters need exactly one of `rate` or `burst`",
+                ),
+            }
+
+            let valid_range = |range: RangeDesc, min: f32| {
+                let range = range.to_range();
+                range.min >= min && range.min <= range.max
+            };
+
+            check_value(
+                &mut check,
+                Some(&emitter.speed),
+                |speed| valid_range(*speed, 0.0),
+                "`speed` must be a non negative value or an ascending [min, max]",
+            );
+
+            check_value(
+                &mut check,
+                Some(&emitter.radius),
+                |radius| radius.is_valid(),
+                radius_message,
+            );
+
+            check_value(
+                &mut check,
+                emitter.material.as_ref(),
+                known_material,
+                "`material` isn't defined in `materials`",
+            );
+
+            check_value(
+                &mut check,
+                emitter.mass.as_ref(),
+                |mass| valid_range(*mass, f32::MIN_POSITIVE),
+                "`mass` must be a positive value or an ascending [min, max]",
+            );
+
+            check_value(
+                &mut check,
+                emitter.color.as_ref(),
+                |color| {
+                    color
+                        .iter()
+                        .all(|c| valid_range(*c, 0.0) && c.to_range().max <= 1.0)
+                },
+                "`color` components must be between 0 and 1",
+            );
+
+            check_value(
+                &mut check,
+                emitter.lifetime.as_ref(),
+                |lifetime| *lifetime > 0.0,
+                "`lifetime` must be positive",
+            );
+
+            check_value(
+                &mut check,
+                emitter.fade_color.as_ref(),
+                valid_color,
+                "`fade_color` components must be between 0 and 1",
+            );
+        }
+
+        for collider in &self.colliders {
+            let start = collider.span().start;
+            let collider = collider.get_ref();
+
+            match collider.shape() {
+                Some(ColliderShape::Segment { a, b }) => {
+                    check(a != b, start, "`segment` needs two different points")
+                }
+
+                Some(ColliderShape::Aabb { min, max }) => check(
+                    min.cmplt(max).all(),
+                    start,
+                    "`aabb` needs `min` below and left of `max`",
+                ),
+
+                Some(ColliderShape::Circle { radius, .. }) => {
+                    check(radius > 0.0, start, "`circle` radius must be positive")
+                }
+
+                Some(ColliderShape::Polygon(vertices)) => check(
+                    colliders::is_convex(&vertices),
+                    start,
+                    "`polygon` needs at least 3 vertices forming a convex shape",
+                ),
+
+                None => check(
+                    false,
+                    start,
+                    "colliders need exactly one of `segment`, `aabb`, `circle` or `polygon`",
+                ),
+            }
+
+            check_surface(
+                &mut check,
+                Surface {
+                    restitution: collider.restitution,
+                    friction: collider.friction,
+                },
+                start,
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            errors.sort_by_key(|e| e.line);
+            Err(SceneErrors(errors))
+        }
+    }
+
+    /// Builds the simulation, expects a validated scene
+    pub fn into_simulation(self) -> Simulation {
+        let [width, height] = self.size.into_inner();
+        let seed = self.seed.unwrap_or_else(rand::random);
+
+        let mut simulation = Simulation::new(glam::vec2(width as _, height as _), seed);
+
+        if let Some(boundary) = self.boundary {
+            let [left, right, top, bottom] = boundary.get_ref().boundaries().unwrap();
+
+            simulation.set_domain(Domain {
+                left,
+                right,
+                top,
+                bottom,
+                ..simulation.domain()
+            });
+        }
+
+        if let Some(integrator) = self.integrator {
+            let integrator: Integrator = integrator.get_ref().parse().unwrap();
+            simulation.resources.insert(integrator);
+        }
+
+        if let Some(broad_phase) = self.broad_phase {
+            let broad_phase: BroadPhaseKind = broad_phase.get_ref().parse().unwrap();
+            simulation.resources.insert(broad_phase);
+        }
+
+        if let Some(capacity) = self.quad_capacity {
+            simulation
+                .resources
+                .insert(QuadCapacity(capacity.into_inner()));
+        }
+
+        if let Some(passes) = self.contact_passes {
+            simulation
+                .resources
+                .insert(ContactPasses(passes.into_inner()));
+        }
+
+        if let Some(gravitation) = self.gravitation {
+            let desc = gravitation.into_inner();
+            let default = Gravitation::default();
+
+            simulation.resources.insert(Gravitation {
+                constant: desc.constant,
+                theta: desc.theta.unwrap_or(default.theta),
+                softening: desc.softening.unwrap_or(default.softening),
+            });
+        }
+
+        let force_fields = self
+            .force_fields
+            .into_iter()
+            .map(|field| match field.into_inner() {
+                ForceFieldDesc::Gravity(accel) => ForceField::Gravity(accel.into()),
+                ForceFieldDesc::LinearDrag(k) => ForceField::LinearDrag(k),
+                ForceFieldDesc::QuadraticDrag(k) => ForceField::QuadraticDrag(k),
+                ForceFieldDesc::Wind { velocity, drag } => ForceField::Wind {
+                    velocity: velocity.into(),
+                    drag,
+                },
+            })
+            .collect();
+
+        simulation.resources.insert(ForceFields(force_fields));
+
+        for material in &self.materials {
+            let material = material.get_ref();
+            simulation.add_material(&material.name, material.material());
+        }
+
+        for particle in &self.particles {
+            let particle = particle.get_ref();
+
+            spawn_particle(
+                &mut simulation,
+                particle.position.into(),
+                particle.velocity.into(),
+                &particle.look(),
+            );
+        }
+
+        for lattice in &self.lattices {
+            let lattice = lattice.get_ref();
+
+            for row in 0..*lattice.rows.get_ref() {
+                for column in 0..*lattice.columns.get_ref() {
+                    let offset = glam::vec2(column as f32, row as f32)
+                        * Vec2::from(*lattice.spacing.get_ref());
+
+                    let (velocity, radius) = {
+                        let jitter = lattice
+                            .velocity_jitter
+                            .as_ref()
+                            .map_or(0.0, |j| *j.get_ref());
+                        let mut velocity = Vec2::from(lattice.velocity);
+                        let mut rng = simulation.resources.get_mut::<SimulationRng>().unwrap();
+
+                        if jitter > 0.0 {
+                            velocity.x += rng.random_range(-jitter..=jitter);
+                            velocity.y += rng.random_range(-jitter..=jitter);
+                        }
+
+                        let radius = lattice
+                            .radius
+                            .get_ref()
+                            .to_distribution()
+                            .sample(&mut **rng);
+                        (velocity, radius)
+                    };
+
+                    spawn_particle(
+                        &mut simulation,
+                        Vec2::from(lattice.origin) + offset,
+                        velocity,
+                        &lattice.look(radius),
+                    );
+                }
+            }
+        }
+
+        for emitter in self.emitters {
+            let desc = emitter.into_inner();
+            let mut emitter = Emitter::new(desc.position.into());
+
+            emitter.mode = match (
+                desc.rate.map(Spanned::into_inner),
+                desc.burst.map(Spanned::into_inner),
+            ) {
+                (Some(rate), _) => EmissionMode::Continuous(rate),
+                (None, Some(burst)) => EmissionMode::Burst {
+                    count: burst.count,
+                    interval: burst.interval.unwrap_or(f32::INFINITY),
+                },
+                (None, None) => EmissionMode::Continuous(0.0),
+            };
+
+            emitter.shape = match desc.shape {
+                ShapeDesc::Point => SpawnShape::Point,
+                ShapeDesc::Circle(radius) => SpawnShape::Circle { radius },
+                ShapeDesc::Line(offset) => SpawnShape::Line {
+                    offset: offset.into(),
+                },
+                ShapeDesc::Rectangle(size) => SpawnShape::Rectangle { size: size.into() },
+            };
+
+            emitter.direction = desc.direction.to_radians();
+            emitter.spread = desc.spread.to_radians();
+            emitter.speed = desc.speed.into_inner().to_range();
+            emitter.radius = desc.radius.into_inner().to_distribution();
+            emitter.mass = desc.mass.map(|mass| mass.into_inner().to_range());
+            emitter.color = desc
+                .color
+                .map(|color| color.into_inner().map(RangeDesc::to_range));
+            emitter.lifetime = desc.lifetime.map(Spanned::into_inner);
+            emitter.fade_color = desc.fade_color.map(Spanned::into_inner).unwrap_or_default();
+            emitter.material = material_id(
+                &simulation,
+                desc.material.as_ref().map(|m| m.get_ref().as_str()),
+            );
+
+            simulation.add_emitter(emitter);
+        }
+
+        for collider in self.colliders {
+            let desc = collider.into_inner();
+
+            simulation.add_collider(Collider {
+                shape: desc.shape().unwrap(),
+                surface: Surface {
+                    restitution: desc.restitution,
+                    friction: desc.friction,
+                },
+            });
+        }
+
+        simulation
+    }
+}
+
+/// Id of a material the scene defines, `None` being the default one
+fn material_id(simulation: &Simulation, name: Option<&str>) -> MaterialId {
+    name.and_then(|name| simulation.materials().id(name))
+        .unwrap_or_default()
+}
+
+fn spawn_particle(simulation: &mut Simulation, pos: Vec2, velocity: Vec2, look: &ParticleLook) {
+    let id = material_id(simulation, look.material.map(|m| m.get_ref().as_str()));
+    let mass = look.mass.map(|mass| *mass.get_ref());
+    let lifetime = look.lifetime.map(|lifetime| *lifetime.get_ref());
+    let fade_color = look.fade_color.map(|color| *color.get_ref());
+
+    let (color, mass) = {
+        let materials = simulation.materials();
+        let material = materials.get(id);
+        let mut rng = simulation.resources.get_mut::<SimulationRng>().unwrap();
+
+        (
+            look.color
+                .map_or_else(|| material.color(&mut **rng), |color| *color.get_ref()),
+            mass.unwrap_or_else(|| material.mass(look.radius)),
+        )
+    };
+
+    let [red, green, blue] = color;
+    let entity = simulation.spawn(
+        [pos.x, pos.y, look.radius, red, green, blue],
+        velocity,
+        mass,
+    );
+
+    let mut entry = simulation.world.entry(entity).unwrap();
+    entry.add_component(id);
+
+    if let Some(duration) = lifetime {
+        entry.add_component(Lifetime::new(duration));
+        entry.add_component(Fade {
+            start_color: color,
+            end_color: fade_color.unwrap_or([0.0; 3]),
+            start_radius: look.radius,
+            end_radius: 0.0,
+        });
+    }
+}
+
+/// Checks a value where it's given, reporting the line it's on
+fn check_value<T>(
+    check: &mut dyn FnMut(bool, usize, &str),
+    value: Option<&Spanned<T>>,
+    valid: impl Fn(&T) -> bool,
+    message: &str,
+) {
+    if let Some(value) = value {
+        check(valid(value.get_ref()), value.span().start, message);
+    }
+}
+
+/// 1-based line number of a byte offset
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
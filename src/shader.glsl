@@ -25,3 +25,23 @@ flat in vec3 color;
 void main() {
     frag_color = vec4(color, 1.0);
 }
+
+-- collider_vertex
+#version 330 core
+
+layout(location = 0) in vec2 position;
+
+uniform mat4 ortho;
+
+void main() {
+    gl_Position = ortho * vec4(position, 0.0, 1.0);
+}
+
+-- collider_fragment
+#version 330 core
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = vec4(0.8, 0.8, 0.8, 1.0);
+}
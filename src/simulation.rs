@@ -0,0 +1,252 @@
+use std::ops::Deref;
+
+use super::*;
+use systems as sys;
+
+/// The whole simulation state, independent of any window or GPU context.
+///
+/// Particle positions, radii and colors live in the [`InstanceData`] resource
+/// while everything else is stored as components on the legion `World`.
+pub struct Simulation {
+    pub world: World,
+    pub resources: Resources,
+    pub timestep: FixedTimestep,
+    schedule: Schedule,
+
+    /// Positions from before the last step of `advance`, used for interpolation
+    previous: Vec<Vec2>,
+}
+
+impl Simulation {
+    /// Reflecting on every edge of a domain of `size`, see `set_domain`
+    pub fn new(size: Vec2, seed: u64) -> Self {
+        let schedule = Schedule::builder()
+            .add_system(sys::emit_particles_system())
+            .add_system(sys::mutual_gravity_system(QuadTree::new(
+                32,
+                Rect::default(),
+            )))
+            .add_system(sys::integrate_system())
+            .add_system(sys::resolve_collisions_system(
+                QuadTree::new(32, Rect::default()),
+                SpatialGrid::new(1.0, 0),
+            ))
+            .add_system(sys::collide_with_colliders_system())
+            .add_system(sys::age_particles_system())
+            .add_system(sys::apply_boundaries_system())
+            .build();
+
+        let mut resources = Resources::default();
+        resources.insert(InstanceData::default());
+        resources.insert(DespawnQueue::default());
+        resources.insert(Integrator::default());
+        resources.insert(ForceFields::default());
+        resources.insert(Gravitation::default());
+        resources.insert(BroadPhaseKind::default());
+        resources.insert(QuadCapacity(32));
+        resources.insert(ContactPasses(4));
+        resources.insert(SimulationRng::new(seed));
+        resources.insert(Domain::new(size));
+        resources.insert(Materials::default());
+
+        Self {
+            world: World::default(),
+            resources,
+            timestep: FixedTimestep::default(),
+            schedule,
+            previous: vec![],
+        }
+    }
+
+    /// Adds a particle of the default material, `instance` is laid out as described
+    /// by `FLOATS_PER_INSTANCE`. Insert a different `MaterialId` to change that.
+    pub fn spawn(
+        &mut self,
+        instance: [f32; FLOATS_PER_INSTANCE],
+        velocity: Vec2,
+        mass: f32,
+    ) -> Entity {
+        let mut instances = self.resources.get_mut::<InstanceData>().unwrap();
+
+        let entity = self.world.push((
+            // used as an index into the instance data in systems
+            EntityIndex(instances.len()),
+            Velocity(velocity),
+            Mass(mass),
+            Force::default(),
+            MaterialId::default(),
+        ));
+
+        instances.push(instance, entity);
+        entity
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) -> Entity {
+        self.world.push((emitter,))
+    }
+
+    pub fn clear_emitters(&mut self) {
+        let emitters = <Entity>::query()
+            .filter(component::<Emitter>())
+            .iter(&self.world)
+            .copied()
+            .collect::<Vec<_>>();
+
+        for entity in emitters {
+            self.world.remove(entity);
+        }
+    }
+
+    /// Adds a material or replaces the one called `name`, see `Materials::insert`
+    pub fn add_material(&mut self, name: &str, material: Material) -> MaterialId {
+        self.resources
+            .get_mut::<Materials>()
+            .unwrap()
+            .insert(name, material)
+    }
+
+    pub fn materials(&self) -> impl Deref<Target = Materials> + '_ {
+        self.resources.get::<Materials>().unwrap()
+    }
+
+    pub fn add_collider(&mut self, collider: Collider) -> Entity {
+        self.world.push((collider,))
+    }
+
+    pub fn colliders(&self) -> Vec<Collider> {
+        <&Collider>::query().iter(&self.world).cloned().collect()
+    }
+
+    /// Removes a particle right away, the last particle takes over its instance slot.
+    /// Returns false if `entity` isn't a particle.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        let Some(index) = self
+            .world
+            .entry_ref(entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<EntityIndex>().ok().map(|i| i.0))
+        else {
+            return false;
+        };
+
+        let mut instances = self.resources.get_mut::<InstanceData>().unwrap();
+        let last = instances.len() - 1;
+
+        if let Some(moved) = instances.swap_remove(index) {
+            let mut entry = self.world.entry(moved).unwrap();
+            entry.get_component_mut::<EntityIndex>().unwrap().0 = index;
+
+            // keep the interpolation state lined up with the instances
+            if index < self.previous.len() {
+                self.previous[index] = match self.previous.get(last) {
+                    Some(pos) => *pos,
+                    None => Vec2::from_slice(instances.get(index)),
+                };
+            }
+        }
+
+        self.previous.truncate(last);
+        self.world.remove(entity)
+    }
+
+    /// Particles overlapping the circle at `centre`
+    pub fn particles_within(&self, centre: Vec2, radius: f32) -> Vec<Entity> {
+        let instances = self.instances();
+
+        (0..instances.len())
+            .filter(|&index| {
+                let [x, y, r, ..] = *instances.get(index);
+                cc_intersection(glam::vec2(x, y), r, centre, radius)
+            })
+            .map(|index| instances.owner(index))
+            .collect()
+    }
+
+    /// Advances the simulation by `dt` seconds
+    pub fn step(&mut self, dt: f32) {
+        self.resources.insert(DeltaTime(dt));
+        self.schedule.execute(&mut self.world, &mut self.resources);
+
+        let despawned = std::mem::take(&mut self.resources.get_mut::<DespawnQueue>().unwrap().0);
+        for entity in despawned {
+            self.despawn(entity);
+        }
+    }
+
+    /// Runs as many fixed steps as `frame_dt` seconds of real time cover and
+    /// returns how far (`0.0..1.0`) the leftover time is into the next step
+    pub fn advance(&mut self, frame_dt: f32) -> f32 {
+        let steps = self.timestep.advance(frame_dt);
+
+        for i in 0..steps {
+            if i == steps - 1 {
+                let instances = self.resources.get::<InstanceData>().unwrap();
+
+                self.previous.clear();
+                self.previous.extend(
+                    (0..instances.len()).map(|index| Vec2::from_slice(instances.get(index))),
+                );
+            }
+
+            self.step(self.timestep.step);
+        }
+
+        self.timestep.alpha()
+    }
+
+    /// Writes the instance data with positions blended between the last two
+    /// states of `advance`, `out` must hold at least `len() * FLOATS_PER_INSTANCE` floats
+    pub fn write_interpolated(&self, alpha: f32, out: &mut [f32]) {
+        let instances = self.instances();
+        let out = &mut out[..instances.as_slice().len()];
+
+        out.copy_from_slice(instances.as_slice());
+
+        // blending across the domain would draw wrapped particles in between for a frame
+        let max_jump = self.domain().size * 0.5;
+
+        // particles spawned after the last step have no previous state
+        let count = self.previous.len().min(instances.len());
+        for (index, instance) in out
+            .chunks_exact_mut(FLOATS_PER_INSTANCE)
+            .enumerate()
+            .take(count)
+        {
+            let current = Vec2::from_slice(instance);
+            let previous = self.previous[index];
+
+            if (current - previous).abs().cmpgt(max_jump).any() {
+                continue;
+            }
+
+            let pos = previous.lerp(current, alpha);
+
+            instance[..2].copy_from_slice(&pos.to_array());
+        }
+    }
+
+    pub fn instances(&self) -> impl Deref<Target = InstanceData> + '_ {
+        self.resources.get::<InstanceData>().unwrap()
+    }
+
+    /// Number of particles
+    pub fn len(&self) -> usize {
+        self.instances().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.resources.get::<SimulationRng>().unwrap().seed()
+    }
+
+    pub fn domain(&self) -> Domain {
+        *self.resources.get::<Domain>().unwrap()
+    }
+
+    pub fn set_domain(&mut self, domain: Domain) {
+        self.resources.insert(domain);
+    }
+}
@@ -0,0 +1,294 @@
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use forces::ForceField;
+
+/// Bumped whenever the layout of `Snapshot` changes
+pub const SNAPSHOT_VERSION: u32 = 6;
+
+const MAGIC: &[u8; 4] = b"PSIM";
+
+/// Everything needed to resume a simulation, saved either as a binary CBOR
+/// file or, for paths ending in `.ron`, as human readable RON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub domain: Domain,
+    pub rng_seed: u64,
+    pub rng_word_pos: u128,
+    pub materials: Materials,
+    pub integrator: Integrator,
+    pub force_fields: Vec<ForceField>,
+    pub gravitation: Gravitation,
+    pub broad_phase: BroadPhaseKind,
+    pub quad_capacity: usize,
+    pub contact_passes: usize,
+    pub colliders: Vec<Collider>,
+    pub emitters: Vec<Emitter>,
+
+    /// In the order the world iterates them, a restored simulation keeps it so
+    /// its steps give exactly the same results as the captured one's
+    pub particles: Vec<SnapshotParticle>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotParticle {
+    /// Slot in the instance data, every slot belongs to exactly one particle
+    pub index: usize,
+    pub instance: [f32; FLOATS_PER_INSTANCE],
+    pub velocity: [f32; 2],
+    pub mass: f32,
+    pub material: MaterialId,
+    pub lifetime: Option<Lifetime>,
+    pub fade: Option<Fade>,
+}
+
+impl Snapshot {
+    pub fn capture(simulation: &Simulation) -> Self {
+        let instances = simulation.instances();
+        let rng = simulation.resources.get::<SimulationRng>().unwrap();
+
+        let particles = <(
+            &EntityIndex,
+            &Velocity,
+            &Mass,
+            &MaterialId,
+            Option<&Lifetime>,
+            Option<&Fade>,
+        )>::query()
+        .iter(&simulation.world)
+        .map(
+            |(EntityIndex(index), Velocity(vel), Mass(mass), material, lifetime, fade)| {
+                SnapshotParticle {
+                    index: *index,
+                    instance: *instances.get(*index),
+                    velocity: vel.to_array(),
+                    mass: *mass,
+                    material: *material,
+                    lifetime: lifetime.copied(),
+                    fade: fade.copied(),
+                }
+            },
+        )
+        .collect();
+
+        Self {
+            version: SNAPSHOT_VERSION,
+            domain: simulation.domain(),
+            rng_seed: rng.seed(),
+            rng_word_pos: rng.word_pos(),
+            materials: simulation.materials().clone(),
+            integrator: *simulation.resources.get::<Integrator>().unwrap(),
+            force_fields: simulation.resources.get::<ForceFields>().unwrap().0.clone(),
+            gravitation: *simulation.resources.get::<Gravitation>().unwrap(),
+            broad_phase: *simulation.resources.get::<BroadPhaseKind>().unwrap(),
+            quad_capacity: simulation.resources.get::<QuadCapacity>().unwrap().0,
+            contact_passes: simulation.resources.get::<ContactPasses>().unwrap().0,
+            colliders: simulation.colliders(),
+            emitters: <&Emitter>::query()
+                .iter(&simulation.world)
+                .copied()
+                .collect(),
+            particles,
+        }
+    }
+
+    /// Builds a new simulation from the snapshot
+    pub fn restore(&self) -> Simulation {
+        let mut simulation = Simulation::new(self.domain.size, self.rng_seed);
+        simulation.set_domain(self.domain);
+
+        let resources = &mut simulation.resources;
+        resources.insert(self.materials.clone());
+        resources.insert(self.integrator);
+        resources.insert(ForceFields(self.force_fields.clone()));
+        resources.insert(self.gravitation);
+        resources.insert(self.broad_phase);
+        resources.insert(QuadCapacity(self.quad_capacity));
+        resources.insert(ContactPasses(self.contact_passes));
+        resources.insert(SimulationRng::from_state(self.rng_seed, self.rng_word_pos));
+
+        for collider in &self.colliders {
+            simulation.add_collider(collider.clone());
+        }
+
+        for emitter in &self.emitters {
+            simulation.add_emitter(*emitter);
+        }
+
+        // particles are added the way they were spawned, in the order they were
+        // captured, so the world ends up iterating them in that order again
+        let mut slots = vec![None; self.particles.len()];
+
+        for particle in &self.particles {
+            let entity = simulation.world.push((
+                EntityIndex(particle.index),
+                Velocity(Vec2::from_array(particle.velocity)),
+                Mass(particle.mass),
+                Force::default(),
+                particle.material,
+            ));
+
+            let mut entry = simulation.world.entry(entity).unwrap();
+
+            if let Some(lifetime) = particle.lifetime {
+                entry.add_component(lifetime);
+            }
+
+            if let Some(fade) = particle.fade {
+                entry.add_component(fade);
+            }
+
+            slots[particle.index] = Some((particle.instance, entity));
+        }
+
+        let mut instances = simulation.resources.get_mut::<InstanceData>().unwrap();
+        for (instance, entity) in slots.into_iter().flatten() {
+            instances.push(instance, entity);
+        }
+
+        drop(instances);
+        simulation
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+        if path.ends_with(".ron") {
+            let contents = ron::ser::to_string_pretty(self, Default::default())?;
+            file.write_all(contents.as_bytes())?;
+        } else {
+            self.write_binary(&mut file)?;
+        }
+
+        file.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.ends_with(".ron") {
+            let file = std::io::BufReader::new(std::fs::File::open(path)?);
+            return Ok(Self::read_binary(file)?);
+        }
+
+        let snapshot = ron::from_str::<Self>(&std::fs::read_to_string(path)?)?;
+        snapshot.check()?;
+
+        Ok(snapshot)
+    }
+
+    /// Catches what the file formats can't express, so `restore` can rely on it
+    fn check(&self) -> std::io::Result<()> {
+        check_version(self.version)?;
+
+        // the settings follow the same rules as in a scene
+        let size = self.domain.size;
+        let checks = [
+            (
+                size.is_finite() && size.cmpgt(Vec2::ZERO).all(),
+                "`size` must be positive",
+            ),
+            (self.quad_capacity > 0, "`quad_capacity` must be at least 1"),
+            (
+                self.contact_passes > 0,
+                "`contact_passes` must be at least 1",
+            ),
+        ];
+
+        if let Some((_, message)) = checks.into_iter().find(|(ok, _)| !ok) {
+            return Err(invalid_data(message));
+        }
+
+        // particles of the material would pick their color from nothing
+        if let Some((name, _)) = self
+            .materials
+            .iter()
+            .find(|(_, material)| material.palette.is_empty())
+        {
+            return Err(invalid_data(format!("material `{name}` without colors")));
+        }
+
+        let known = |MaterialId(id)| id < self.materials.len();
+        if let Some(emitter) = self.emitters.iter().find(|e| !known(e.material)) {
+            return Err(invalid_data(format!(
+                "unknown material {}",
+                emitter.material.0
+            )));
+        }
+
+        let mut taken = vec![false; self.particles.len()];
+
+        for particle in &self.particles {
+            if !known(particle.material) {
+                return Err(invalid_data(format!(
+                    "unknown material {}",
+                    particle.material.0
+                )));
+            }
+
+            match taken.get_mut(particle.index) {
+                Some(taken @ false) => *taken = true,
+                _ => {
+                    return Err(invalid_data(format!(
+                        "instance {} is out of range or taken twice",
+                        particle.index
+                    )))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The magic bytes and the little endian version, followed by the snapshot as
+    /// CBOR so the layout can't drift from the RON one
+    pub fn write_binary(&self, mut out: impl Write) -> std::io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&self.version.to_le_bytes())?;
+
+        ciborium::into_writer(self, out).map_err(|e| match e {
+            ciborium::ser::Error::Io(e) => e,
+            ciborium::ser::Error::Value(e) => invalid_data(e),
+        })
+    }
+
+    pub fn read_binary(mut input: impl Read) -> std::io::Result<Self> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(invalid_data("not a snapshot file"));
+        }
+
+        let mut version = [0; 4];
+        input.read_exact(&mut version)?;
+
+        // checked before decoding, older layouts may not decode at all
+        check_version(u32::from_le_bytes(version))?;
+
+        let snapshot: Self = ciborium::from_reader(input).map_err(|e| match e {
+            ciborium::de::Error::Io(e) => e,
+            e => invalid_data(e.to_string()),
+        })?;
+
+        snapshot.check()?;
+        Ok(snapshot)
+    }
+}
+
+fn check_version(version: u32) -> std::io::Result<()> {
+    if version == SNAPSHOT_VERSION {
+        return Ok(());
+    }
+
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("unsupported snapshot version {version}, expected {SNAPSHOT_VERSION}"),
+    ))
+}
+
+fn invalid_data(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.into())
+}
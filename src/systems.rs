@@ -1,43 +1,582 @@
+use rayon::prelude::*;
 use world::SubWorld;
 
 use super::*;
 
 #[system(for_each)]
-pub fn update_positions(
+pub fn emit_particles(
+    emitter: &mut Emitter,
+    cmd: &mut legion::systems::CommandBuffer,
+    #[resource] instances: &mut InstanceData,
+    #[resource] materials: &Materials,
+    #[resource] rng: &mut SimulationRng,
+    #[resource] DeltaTime(dt): &DeltaTime,
+) {
+    for _ in 0..emitter.advance(*dt) {
+        let (instance, velocity, mass) =
+            emitter.sample(materials.get(emitter.material), &mut **rng);
+
+        // the entity only exists once the command buffer is flushed,
+        // its instance data is available right away
+        let entity = cmd.push((
+            EntityIndex(instances.len()),
+            Velocity(velocity),
+            Mass(mass),
+            Force::default(),
+            emitter.material,
+        ));
+
+        instances.push(instance, entity);
+
+        if let Some(duration) = emitter.lifetime {
+            let [.., radius, red, green, blue] = instance;
+
+            cmd.add_component(entity, Lifetime::new(duration));
+            cmd.add_component(
+                entity,
+                Fade {
+                    start_color: [red, green, blue],
+                    end_color: emitter.fade_color,
+                    start_radius: radius,
+                    end_radius: 0.0,
+                },
+            );
+        }
+    }
+}
+
+#[system]
+#[read_component(EntityIndex)]
+#[read_component(Mass)]
+#[write_component(Force)]
+pub fn mutual_gravity(
+    world: &mut SubWorld,
+    #[resource] instances: &InstanceData,
+    #[resource] gravitation: &Gravitation,
+    #[resource] QuadCapacity(capacity): &QuadCapacity,
+    #[state] qt: &mut QuadTree<usize>,
+) {
+    if gravitation.constant == 0.0 {
+        return;
+    }
+
+    let mut query = <(&EntityIndex, &Mass, &mut Force)>::query();
+
+    // (position, mass)
+    let bodies: Vec<(Vec2, f32)> = query
+        .iter_mut(world)
+        .map(|(EntityIndex(index), Mass(mass), _)| {
+            let [x, y, ..] = *instances.get(*index);
+            (glam::vec2(x, y), *mass)
+        })
+        .collect();
+
+    if bodies.is_empty() {
+        return;
+    }
+
+    // unlike collisions, gravity isn't limited to the window
+    let (min, max) = bodies.iter().fold(
+        (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+        |(min, max), (pos, _)| (min.min(*pos), max.max(*pos)),
+    );
+
+    let size = root_size(min, max).max_element();
+    rebuild_tree(
+        qt,
+        *capacity,
+        Rect {
+            left: min.x,
+            top: min.y,
+            width: size,
+            height: size,
+        },
+        bodies
+            .iter()
+            .enumerate()
+            .map(|(body, (pos, _))| (*pos, 0.0, body))
+            .collect(),
+    );
+
+    let mass_of = |body: &usize| bodies[*body].1;
+    qt.compute_mass_distribution(&mass_of);
+
+    let accelerations: Vec<Vec2> = bodies
+        .par_iter()
+        .map(|(pos, _)| {
+            qt.barnes_hut_acceleration(*pos, gravitation.theta, gravitation.softening, &mass_of)
+        })
+        .collect();
+
+    query
+        .iter_mut(world)
+        .zip(bodies.iter().zip(accelerations))
+        .for_each(|((_, _, Force(force)), ((_, mass), accel))| {
+            *force += gravitation.constant * *mass * accel;
+        });
+}
+
+#[system(for_each)]
+#[allow(clippy::too_many_arguments)]
+pub fn integrate(
     EntityIndex(index): &EntityIndex,
-    Velocity(vel): &Velocity,
-    #[resource] ptr: &InstanceDataPtr,
+    Velocity(vel): &mut Velocity,
+    Mass(mass): &Mass,
+    Force(force): &mut Force,
+    #[resource] instances: &mut InstanceData,
+    #[resource] fields: &ForceFields,
     #[resource] DeltaTime(dt): &DeltaTime,
+    #[resource] integrator: &Integrator,
 ) {
-    let dt = *dt;
-    let [pos_x, pos_y, ..] = utils::get_entity(*index, ptr.get_ptr());
+    let [pos_x, pos_y, ..] = instances.get_mut(*index);
+
+    // force fields are evaluated again at every stage of the integrator, forces
+    // accumulated by other systems depend on other particles and are held constant
+    let accel = |_, vel| (*force + fields.force(vel, *mass)) / *mass;
+    let (pos, new_vel) = integrator.step(glam::vec2(*pos_x, *pos_y), *vel, *dt, accel);
 
-    *pos_x += vel.x * dt;
-    *pos_y += vel.y * dt;
+    (*pos_x, *pos_y) = (pos.x, pos.y);
+    *vel = new_vel;
+    *force = Vec2::ZERO;
 }
 
 #[system(for_each)]
-pub fn check_wall_collision(
+pub fn age_particles(
+    entity: &Entity,
     EntityIndex(index): &EntityIndex,
-    vel: &mut Velocity,
-    #[resource] size: &(i32, i32),
-    #[resource] ptr: &InstanceDataPtr,
+    lifetime: &mut Lifetime,
+    fade: Option<&Fade>,
+    #[resource] instances: &mut InstanceData,
+    #[resource] despawn: &mut DespawnQueue,
+    #[resource] DeltaTime(dt): &DeltaTime,
+) {
+    lifetime.age += *dt;
+
+    if lifetime.age >= lifetime.duration {
+        despawn.0.push(*entity);
+        return;
+    }
+
+    let Some(fade) = fade else {
+        return;
+    };
+
+    let t = lifetime.age / lifetime.duration;
+    let [_, _, radius, red, green, blue] = instances.get_mut(*index);
+
+    *radius = fade.start_radius + (fade.end_radius - fade.start_radius) * t;
+
+    for (channel, (start, end)) in [red, green, blue]
+        .into_iter()
+        .zip(fade.start_color.iter().zip(fade.end_color))
+    {
+        *channel = start + (end - start) * t;
+    }
+}
+
+#[system]
+#[read_component(Collider)]
+#[read_component(EntityIndex)]
+#[read_component(MaterialId)]
+#[write_component(Velocity)]
+pub fn collide_with_colliders(
+    world: &mut SubWorld,
+    #[resource] instances: &mut InstanceData,
+    #[resource] materials: &Materials,
 ) {
-    let [pos_x, pos_y, radius, ..] = utils::get_entity(*index, ptr.get_ptr());
+    let colliders = <&Collider>::query()
+        .iter(world)
+        .cloned()
+        .collect::<Vec<_>>();
 
-    if *pos_x - *radius < 0.0 {
-        vel.0.x *= -1.0;
-        *pos_x = *radius;
-    } else if *pos_x + *radius >= size.0 as f32 {
-        vel.0.x *= -1.0;
-        *pos_x = size.0 as f32 - *radius;
+    if colliders.is_empty() {
+        return;
     }
 
-    if *pos_y - *radius < 0.0 {
-        vel.0.y *= -1.0;
-        *pos_y = *radius;
-    } else if *pos_y + *radius >= size.1 as f32 {
-        vel.0.y *= -1.0;
-        *pos_y = size.1 as f32 - *radius;
+    for (EntityIndex(index), Velocity(vel), material) in
+        <(&EntityIndex, &mut Velocity, &MaterialId)>::query().iter_mut(world)
+    {
+        let [pos_x, pos_y, radius, ..] = instances.get_mut(*index);
+        let mut pos = glam::vec2(*pos_x, *pos_y);
+        let surface = materials.get(*material).surface;
+
+        for collider in &colliders {
+            collider.resolve(&mut pos, vel, *radius, surface);
+        }
+
+        (*pos_x, *pos_y) = (pos.x, pos.y);
     }
 }
+
+/// Refills a tree kept by a system, it's only made anew when `QuadCapacity` changed
+/// Size of a quadtree root at `min` that holds everything up to `max`. The far
+/// edges are excluded, and a fixed padding would be rounded away far from the
+/// origin, so it grows with the magnitude of the coordinates.
+fn root_size(min: Vec2, max: Vec2) -> Vec2 {
+    max - min + (min.abs() + max.abs()) * (4.0 * f32::EPSILON) + Vec2::ONE
+}
+
+fn rebuild_tree(
+    tree: &mut QuadTree<usize>,
+    capacity: usize,
+    boundary: Rect,
+    points: Vec<(Vec2, f32, usize)>,
+) {
+    if tree.capacity() != capacity {
+        *tree = QuadTree::new(capacity, boundary.clone());
+    }
+
+    tree.par_rebuild(boundary, points);
+}
+
+/// Bounces, wraps or despawns particles at the edges of the domain
+#[system(for_each)]
+#[allow(clippy::too_many_arguments)]
+pub fn apply_boundaries(
+    entity: &Entity,
+    EntityIndex(index): &EntityIndex,
+    Velocity(vel): &mut Velocity,
+    material: &MaterialId,
+    #[resource] domain: &Domain,
+    #[resource] materials: &Materials,
+    #[resource] instances: &mut InstanceData,
+    #[resource] despawn: &mut DespawnQueue,
+) {
+    let [pos_x, pos_y, radius, ..] = instances.get_mut(*index);
+    let mut pos = glam::vec2(*pos_x, *pos_y);
+    let surface = materials.get(*material).surface;
+
+    if !domain.constrain(&mut pos, vel, *radius, surface) {
+        despawn.0.push(*entity);
+    }
+
+    (*pos_x, *pos_y) = (pos.x, pos.y);
+}
+
+/// Circles closer than this many times the sum of their radii count as touching
+const CONTACT_TOLERANCE: f32 = 1.01;
+
+/// Contacts are gathered while the centres are closer than this many times the sum
+/// of the radii
+const CONTACT_MARGIN: f32 = 1.5;
+
+/// Fewest contacts of a batch solved by one thread, fewer aren't worth handing out
+const CONTACTS_PER_TASK: usize = 256;
+
+#[system]
+#[read_component(EntityIndex)]
+#[read_component(Mass)]
+#[read_component(MaterialId)]
+#[write_component(Velocity)]
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_collisions(
+    world: &mut SubWorld,
+    #[resource] instances: &mut InstanceData,
+    #[resource] materials: &Materials,
+    #[resource] domain: &Domain,
+    #[resource] broad_phase: &BroadPhaseKind,
+    #[resource] QuadCapacity(capacity): &QuadCapacity,
+    #[resource] ContactPasses(passes): &ContactPasses,
+    #[resource] DeltaTime(dt): &DeltaTime,
+    #[state] quadtree: &mut QuadTree<usize>,
+    #[state] grid: &mut SpatialGrid<usize>,
+) {
+    let mut query = <(&EntityIndex, &mut Velocity, &Mass, &MaterialId)>::query();
+
+    // (instance index, velocity, mass, material)
+    let mut bodies: Vec<Body> = query
+        .iter_mut(world)
+        .map(
+            |(EntityIndex(index), Velocity(vel), Mass(mass), material)| {
+                (*index, *vel, *mass, *material)
+            },
+        )
+        .collect();
+
+    if bodies.is_empty() {
+        return;
+    }
+
+    // cohesive particles also pull on neighbours they don't touch yet
+    let reach = if materials
+        .iter()
+        .any(|(_, material)| material.cohesion > 0.0)
+    {
+        material::COHESION_RANGE
+    } else {
+        1.0
+    };
+
+    // pushing circles apart can move them into neighbours they weren't touching
+    // before, so contacts are gathered a bit further out
+    let search = reach.max(CONTACT_MARGIN);
+
+    // open edges let particles leave the domain, so the quadtree covers wherever they
+    // are. Collisions across wrapping edges aren't detected.
+    let (min, max, max_radius) = bodies.iter().fold(
+        (
+            Vec2::splat(f32::INFINITY),
+            Vec2::splat(f32::NEG_INFINITY),
+            0.0f32,
+        ),
+        |(min, max, max_radius), (index, ..)| {
+            let [x, y, radius, ..] = *instances.get(*index);
+            let pos = glam::vec2(x, y);
+            (min.min(pos), max.max(pos), max_radius.max(radius))
+        },
+    );
+
+    // circles are stored with their own radius, so overlaps are found between
+    // any two sizes without widening every search by the largest radius
+    let points = bodies
+        .iter()
+        .enumerate()
+        .map(|(body, (index, ..))| {
+            let [x, y, r, ..] = *instances.get(*index);
+            (glam::vec2(x, y), r * search, body)
+        })
+        .collect::<Vec<_>>();
+
+    // both are kept between steps so refilling them doesn't allocate
+    let circles: &(dyn BroadPhase<usize> + Sync) = match broad_phase {
+        BroadPhaseKind::QuadTree => {
+            let size = root_size(min, max);
+            rebuild_tree(
+                quadtree,
+                *capacity,
+                Rect {
+                    left: min.x,
+                    top: min.y,
+                    width: size.x,
+                    height: size.y,
+                },
+                points,
+            );
+
+            quadtree
+        }
+
+        // cells as wide as the largest circle, so most queries only look at
+        // the neighbouring ones
+        BroadPhaseKind::Grid => {
+            // circles without a radius still need cells with a size
+            grid.clear((2.0 * max_radius * search).max(1.0));
+
+            for (pos, radius, body) in points {
+                grid.push(pos, radius, body);
+            }
+
+            grid
+        }
+    };
+
+    // every pair close enough to interact, once from its lower body index
+    let pairs: Vec<(usize, usize)> = (0..bodies.len())
+        .into_par_iter()
+        .flat_map_iter(|a| {
+            let [x, y, radius, ..] = *instances.get(bodies[a].0);
+
+            circles
+                .query(glam::vec2(x, y), radius * search)
+                .into_iter()
+                .filter(move |b| *b > a)
+                .map(move |b| (a, b))
+        })
+        .collect();
+
+    if reach > 1.0 {
+        // equal and opposite impulses, so momentum is kept
+        let kicks: Vec<(usize, usize, Vec2, Vec2)> = pairs
+            .par_iter()
+            .filter_map(|&(a, b)| {
+                let (index_a, _, mass_a, material_a) = bodies[a];
+                let (index_b, _, mass_b, material_b) = bodies[b];
+                let cohesion = materials.get(material_a).cohesion;
+
+                if material_a != material_b || cohesion == 0.0 {
+                    return None;
+                }
+
+                let [x_a, y_a, radius_a, ..] = *instances.get(index_a);
+                let [x_b, y_b, radius_b, ..] = *instances.get(index_b);
+
+                let delta = glam::vec2(x_b - x_a, y_b - y_a);
+                let distance = delta.length();
+
+                if distance >= (radius_a + radius_b) * reach || distance == 0.0 {
+                    return None;
+                }
+
+                let normal = delta / distance;
+                let impulse = cohesion * dt * mass_a * mass_b / (mass_a + mass_b);
+
+                Some((
+                    a,
+                    b,
+                    normal * (impulse / mass_a),
+                    normal * (impulse / mass_b),
+                ))
+            })
+            .collect();
+
+        for (a, b, kick_a, kick_b) in kicks {
+            bodies[a].1 += kick_a;
+            bodies[b].1 -= kick_b;
+        }
+    }
+
+    // no body is in two contacts of a batch, so a batch is solved in parallel
+    // from the state before it and applied afterwards, the same as solving its
+    // contacts one after the other
+    let batches = batch_pairs(&pairs, bodies.len());
+    let mut solved = vec![];
+
+    // a single pass only lets a contact react to the ones solved before it, stacks
+    // need a few for the floor to hold up everything resting on it
+    for _ in 0..*passes {
+        for batch in &batches {
+            batch
+                .par_iter()
+                .with_min_len(CONTACTS_PER_TASK)
+                .map(|&(a, b)| solve_contact(bodies[a], bodies[b], instances, materials, domain))
+                .collect_into_vec(&mut solved);
+
+            for (&(a, b), solved) in batch.iter().zip(solved.drain(..)) {
+                let Some([(pos_a, vel_a), (pos_b, vel_b)]) = solved else {
+                    continue;
+                };
+
+                instances.get_mut(bodies[a].0)[..2].copy_from_slice(&pos_a.to_array());
+                instances.get_mut(bodies[b].0)[..2].copy_from_slice(&pos_b.to_array());
+
+                bodies[a].1 = vel_a;
+                bodies[b].1 = vel_b;
+            }
+        }
+    }
+
+    query
+        .iter_mut(world)
+        .zip(bodies)
+        .for_each(|((_, Velocity(vel), ..), (_, new_vel, ..))| *vel = new_vel);
+}
+
+/// Instance index, velocity, mass and material of a particle in `resolve_collisions`
+type Body = (usize, Vec2, f32, MaterialId);
+
+/// Splits `pairs` into batches in which no body appears twice, greedily so the
+/// first batches are the largest. Pairs keep their order within a batch.
+fn batch_pairs(pairs: &[(usize, usize)], bodies: usize) -> Vec<Vec<(usize, usize)>> {
+    // bit `i` is set once the body has a contact in batch `i`
+    let mut batched = vec![0u64; bodies];
+    let mut batches: Vec<Vec<(usize, usize)>> = vec![];
+
+    // bodies touching more than 64 others run out of batches, the rest of their
+    // contacts are solved one at a time
+    let mut crowded = vec![];
+
+    for &(a, b) in pairs {
+        let free = !(batched[a] | batched[b]);
+
+        if free == 0 {
+            crowded.push(vec![(a, b)]);
+            continue;
+        }
+
+        // every batch before the first free one is taken, so it exists already
+        let batch = free.trailing_zeros() as usize;
+        batched[a] |= 1 << batch;
+        batched[b] |= 1 << batch;
+
+        if batch == batches.len() {
+            batches.push(vec![]);
+        }
+
+        batches[batch].push((a, b));
+    }
+
+    batches.extend(crowded);
+    batches
+}
+
+/// New positions and velocities of both bodies of a contact, `None` if they're too
+/// far apart to touch
+fn solve_contact(
+    (index_a, vel_a, mass_a, material_a): Body,
+    (index_b, vel_b, mass_b, material_b): Body,
+    instances: &InstanceData,
+    materials: &Materials,
+    domain: &Domain,
+) -> Option<[(Vec2, Vec2); 2]> {
+    let [x_a, y_a, radius_a, ..] = *instances.get(index_a);
+    let [x_b, y_b, radius_b, ..] = *instances.get(index_b);
+
+    let pos_a = glam::vec2(x_a, y_a);
+    let pos_b = glam::vec2(x_b, y_b);
+
+    let delta = pos_b - pos_a;
+    let distance = delta.length();
+    let min_distance = radius_a + radius_b;
+
+    // circles pushed apart by an earlier pass are left just touching, they still
+    // have to stop approaching. Coincident centres have no collision normal.
+    if distance > min_distance * CONTACT_TOLERANCE || distance == 0.0 {
+        return None;
+    }
+
+    // a particle resting on a wall can't be pushed into it, as if it was
+    // infinitely heavy
+    let normal = delta / distance;
+    let (push_a, push_b) = match (
+        domain.blocks(pos_a, radius_a, -normal),
+        domain.blocks(pos_b, radius_b, normal),
+    ) {
+        (true, false) => (f32::INFINITY, mass_b),
+        (false, true) => (mass_a, f32::INFINITY),
+        _ => (mass_a, mass_b),
+    };
+
+    // push the circles apart, the lighter one moves further
+    let overlap = (min_distance - distance).max(0.0);
+    let (inverse_a, inverse_b) = (1.0 / push_a, 1.0 / push_b);
+    let inverse_total = inverse_a + inverse_b;
+
+    let pos_a = pos_a - normal * overlap * (inverse_a / inverse_total);
+    let pos_b = pos_b + normal * overlap * (inverse_b / inverse_total);
+
+    // a blocked particle stands in for the wall, so it's hit as if it wasn't
+    // moving along the normal and keeps its own velocity
+    let hit_a = if push_a.is_infinite() {
+        vel_a - normal * vel_a.dot(normal)
+    } else {
+        vel_a
+    };
+    let hit_b = if push_b.is_infinite() {
+        vel_b - normal * vel_b.dot(normal)
+    } else {
+        vel_b
+    };
+
+    // already separating, e.g. resolved by an earlier pair or pass
+    if (hit_b - hit_a).dot(delta) >= 0.0 {
+        return Some([(pos_a, vel_a), (pos_b, vel_b)]);
+    }
+
+    let (hit_a, hit_b) = utils::process_collision(
+        hit_a,
+        hit_b,
+        pos_a,
+        pos_b,
+        push_a,
+        push_b,
+        materials
+            .get(material_a)
+            .surface
+            .combine(materials.get(material_b).surface),
+    );
+
+    let vel_a = if push_a.is_infinite() { vel_a } else { hit_a };
+    let vel_b = if push_b.is_infinite() { vel_b } else { hit_b };
+
+    Some([(pos_a, vel_a), (pos_b, vel_b)])
+}
@@ -0,0 +1,48 @@
+/// Turns variable frame times into a whole number of fixed size steps,
+/// carrying the remainder over to the next frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedTimestep {
+    /// Simulated seconds per step
+    pub step: f32,
+
+    /// Upper bound on steps per frame, time beyond that is dropped
+    /// so a long stall doesn't make the simulation spiral
+    pub max_substeps: usize,
+
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32, max_substeps: usize) -> Self {
+        Self {
+            step,
+            max_substeps,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds `frame_dt` seconds and returns how many steps should be run
+    pub fn advance(&mut self, frame_dt: f32) -> usize {
+        self.accumulator += frame_dt;
+
+        let steps = (self.accumulator / self.step) as usize;
+        if steps > self.max_substeps {
+            self.accumulator %= self.step;
+            return self.max_substeps;
+        }
+
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    /// How far the leftover time is into the next step, in the range `0.0..1.0`
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(1.0 / 120.0, 8)
+    }
+}
@@ -1,85 +1,5 @@
 use super::*;
 
-pub unsafe fn reallocate_instance_vbo(
-    gl: &glow::Context,
-    buffer_capacity: usize,
-    old_capacity: usize,
-    instance_data_ptr: &mut *mut f32,
-    instance_vbo: &mut glow::NativeBuffer,
-    vao: glow::NativeVertexArray,
-) {
-    let new_vbo = gl.create_buffer().unwrap();
-
-    gl.bind_buffer(glow::ARRAY_BUFFER, Some(new_vbo));
-    gl.buffer_storage(
-        glow::ARRAY_BUFFER,
-        (buffer_capacity * INSTANCE_DATA_STRIDE) as _,
-        None,
-        BUFFER_ACCESS_FLAGS,
-    );
-
-    let new_ptr = gl.map_buffer_range(
-        glow::ARRAY_BUFFER,
-        0,
-        (buffer_capacity * INSTANCE_DATA_STRIDE) as _,
-        BUFFER_ACCESS_FLAGS,
-    ) as *mut f32;
-
-    std::ptr::copy_nonoverlapping(
-        *instance_data_ptr,
-        new_ptr,
-        old_capacity * FLOATS_PER_INSTANCE,
-    );
-
-    gl.bind_vertex_array(Some(vao));
-
-    gl.bind_buffer(glow::ARRAY_BUFFER, Some(*instance_vbo));
-    gl.unmap_buffer(glow::ARRAY_BUFFER);
-    gl.delete_buffer(*instance_vbo);
-
-    gl.bind_buffer(glow::ARRAY_BUFFER, Some(new_vbo));
-
-    setup_instance_attributes(gl);
-
-    *instance_data_ptr = new_ptr;
-    *instance_vbo = new_vbo;
-
-    // unbind
-    // gl.bind_vertex_array(None);
-    // gl.bind_buffer(glow::ARRAY_BUFFER, None);
-}
-
-pub unsafe fn setup_instance_attributes(gl: &glow::Context) {
-    // position
-    gl.enable_vertex_attrib_array(1);
-    gl.vertex_attrib_divisor(1, 1);
-    gl.vertex_attrib_pointer_f32(1, 2, glow::FLOAT, false, INSTANCE_DATA_STRIDE as _, 0);
-
-    // radius
-    gl.enable_vertex_attrib_array(2);
-    gl.vertex_attrib_divisor(2, 1);
-    gl.vertex_attrib_pointer_f32(
-        2,
-        1,
-        glow::FLOAT,
-        false,
-        INSTANCE_DATA_STRIDE as _,
-        (std::mem::size_of::<f32>() * 2) as _,
-    );
-
-    // color
-    gl.enable_vertex_attrib_array(3);
-    gl.vertex_attrib_divisor(3, 1);
-    gl.vertex_attrib_pointer_f32(
-        3,
-        3,
-        glow::FLOAT,
-        false,
-        INSTANCE_DATA_STRIDE as _,
-        (std::mem::size_of::<f32>() * 3) as _,
-    );
-}
-
 pub fn generate_circle(point_count: u32) -> (Vec<f32>, Vec<u32>) {
     let mut vertices: Vec<f32> = vec![];
 
@@ -98,26 +18,49 @@ pub fn generate_circle(point_count: u32) -> (Vec<f32>, Vec<u32>) {
     (vertices, indices)
 }
 
-pub fn get_entity<'a>(index: usize, ptr: *mut f32) -> [&'a mut f32; FLOATS_PER_INSTANCE] {
-    unsafe {
-        let [x, y, r, red, green, blue] = std::slice::from_raw_parts_mut(
-            ptr.add(index * FLOATS_PER_INSTANCE),
-            FLOATS_PER_INSTANCE,
-        ) else {
-            std::hint::unreachable_unchecked()
-        };
+/// Largest viewport with the aspect ratio of `domain`, centred in the window,
+/// as `(x, y, width, height)`
+pub fn letterbox(window: (i32, i32), domain: Vec2) -> (i32, i32, i32, i32) {
+    let window_size = glam::vec2(window.0 as _, window.1 as _);
+    let size = domain * (window_size / domain).min_element();
+    let offset = (window_size - size) * 0.5;
+
+    (offset.x as _, offset.y as _, size.x as _, size.y as _)
+}
+
+/// Position in the domain under a point of the window, see `letterbox`
+pub fn window_to_domain(window: (i32, i32), point: (f64, f64), domain: Vec2) -> Vec2 {
+    let (x, y, width, height) = letterbox(window, domain);
 
-        [x, y, r, red, green, blue]
-    }
+    let offset = glam::vec2(point.0 as f32 - x as f32, point.1 as f32 - y as f32);
+    offset / glam::vec2(width as _, height as _) * domain
 }
 
-pub fn process_collision(v1: Vec2, v2: Vec2, s1: Vec2, s2: Vec2, m1: f32, m2: f32) -> (Vec2, Vec2) {
-    (
-        v1 - (2.0 * m2) / (m1 + m2)
-            * ((v1 - v2).dot(s1 - s2) / (s1 - s2).length_squared())
-            * (s1 - s2),
-        v2 - (2.0 * m1) / (m1 + m1)
-            * ((v2 - v1).dot(s2 - s1) / (s2 - s1).length_squared())
-            * (s2 - s1),
-    )
+/// Velocities after a collision between particles at `s1` and `s2`, `surface` being
+/// the combined surface of both. Friction only acts along the contact tangent since
+/// particles don't spin.
+pub fn process_collision(
+    v1: Vec2,
+    v2: Vec2,
+    s1: Vec2,
+    s2: Vec2,
+    m1: f32,
+    m2: f32,
+    surface: Surface,
+) -> (Vec2, Vec2) {
+    let normal = (s2 - s1).normalize_or_zero();
+    let relative = v2 - v1;
+    let normal_speed = relative.dot(normal);
+
+    // impulses, divided by the masses to get velocity changes
+    let inverse_mass = 1.0 / m1 + 1.0 / m2;
+    let normal_impulse = -(1.0 + surface.restitution) * normal_speed / inverse_mass;
+
+    let tangent_vel = relative - normal * normal_speed;
+    let friction_impulse =
+        (surface.friction * normal_impulse.abs()).min(tangent_vel.length() / inverse_mass);
+
+    let impulse = normal * normal_impulse - tangent_vel.normalize_or_zero() * friction_impulse;
+
+    (v1 - impulse / m1, v2 + impulse / m2)
 }
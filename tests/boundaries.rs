@@ -0,0 +1,136 @@
+use glam::Vec2;
+use legion::{Entity, EntityStore, IntoQuery};
+use particle_simulator::boundary::{Boundary, Domain};
+use particle_simulator::components::{EntityIndex, Velocity};
+use particle_simulator::emitter::Emitter;
+use particle_simulator::{utils, Simulation};
+
+const SIZE: Vec2 = glam::vec2(400.0, 300.0);
+
+fn simulation(boundary: Boundary) -> Simulation {
+    let mut simulation = Simulation::new(SIZE, 0);
+    simulation.set_domain(Domain::with_boundary(SIZE, boundary));
+    simulation
+}
+
+fn spawn(simulation: &mut Simulation, pos: Vec2, vel: Vec2) -> Entity {
+    simulation.spawn([pos.x, pos.y, 5.0, 1.0, 1.0, 1.0], vel, 1.0)
+}
+
+fn position(simulation: &Simulation, entity: Entity) -> Vec2 {
+    let index = simulation
+        .world
+        .entry_ref(entity)
+        .unwrap()
+        .get_component::<EntityIndex>()
+        .unwrap()
+        .0;
+
+    Vec2::from_slice(simulation.instances().get(index))
+}
+
+fn steps(simulation: &mut Simulation, count: usize) {
+    for _ in 0..count {
+        simulation.step(1.0 / 120.0);
+    }
+}
+
+#[test]
+fn wrapping_brings_particles_back_through_the_opposite_edge() {
+    let mut simulation = simulation(Boundary::Wrap);
+    let right = spawn(
+        &mut simulation,
+        glam::vec2(390.0, 150.0),
+        glam::vec2(120.0, 0.0),
+    );
+    let up = spawn(
+        &mut simulation,
+        glam::vec2(100.0, 10.0),
+        glam::vec2(0.0, -120.0),
+    );
+
+    // a quarter of a second covers 30 units
+    steps(&mut simulation, 30);
+
+    let right = position(&simulation, right);
+    assert!((right - glam::vec2(20.0, 150.0)).length() < 1e-2, "{right}");
+
+    let up = position(&simulation, up);
+    assert!((up - glam::vec2(100.0, 280.0)).length() < 1e-2, "{up}");
+}
+
+#[test]
+fn absorbing_edges_despawn_particles_and_compact_their_slots() {
+    let mut simulation = simulation(Boundary::Absorb);
+    let leaving = spawn(
+        &mut simulation,
+        glam::vec2(10.0, 150.0),
+        glam::vec2(-120.0, 0.0),
+    );
+    let first = spawn(&mut simulation, glam::vec2(100.0, 150.0), Vec2::ZERO);
+    let last = spawn(&mut simulation, glam::vec2(200.0, 150.0), Vec2::ZERO);
+
+    steps(&mut simulation, 30);
+
+    assert_eq!(simulation.len(), 2);
+    assert!(simulation.world.entry_ref(leaving).is_err());
+
+    // the last particle moved into the freed slot, and every slot matches its owner
+    assert_eq!(simulation.instances().owner(0), last);
+    assert_eq!(position(&simulation, last), glam::vec2(200.0, 150.0));
+    assert_eq!(position(&simulation, first), glam::vec2(100.0, 150.0));
+}
+
+#[test]
+fn open_edges_let_particles_keep_going() {
+    let mut simulation = simulation(Boundary::Open);
+    let vel = glam::vec2(120.0, -120.0);
+    let particle = spawn(&mut simulation, glam::vec2(390.0, 10.0), vel);
+
+    steps(&mut simulation, 120);
+
+    let pos = position(&simulation, particle);
+    assert!((pos - glam::vec2(510.0, -110.0)).length() < 1e-2, "{pos}");
+
+    let after = <&Velocity>::query()
+        .iter(&simulation.world)
+        .next()
+        .unwrap()
+        .0;
+    assert_eq!(after, vel);
+}
+
+/// Steps a simulation while an emitter follows a cursor resting on the same spot
+/// of the domain, in a window of `window` pixels
+fn drawn_in(window: (i32, i32)) -> Vec<u32> {
+    let mut simulation = Simulation::new(SIZE, 7);
+
+    // a quarter of the way across the domain, wherever the letterbox puts it
+    let (x, y, width, height) = utils::letterbox(window, SIZE);
+    let cursor = (
+        x as f64 + width as f64 * 0.25,
+        y as f64 + height as f64 * 0.5,
+    );
+
+    let position = utils::window_to_domain(window, cursor, SIZE);
+    simulation.add_emitter(Emitter::new(position));
+
+    steps(&mut simulation, 120);
+
+    let instances = simulation.instances();
+    instances
+        .as_slice()
+        .iter()
+        .map(|value| value.to_bits())
+        .collect()
+}
+
+#[test]
+fn physics_doesnt_depend_on_the_window_size() {
+    let reference = drawn_in((400, 300));
+    assert!(!reference.is_empty());
+
+    for window in [(800, 600), (1920, 1080), (300, 900)] {
+        assert_eq!(drawn_in(window), reference, "window of {window:?}");
+    }
+}
@@ -0,0 +1,119 @@
+use glam::Vec2;
+use particle_simulator::broad_phase::{BroadPhase, BroadPhaseKind, SpatialGrid};
+use particle_simulator::quadtree::{cc_intersection, QuadTree, Rect};
+use particle_simulator::{Scene, Simulation};
+
+/// Mostly small circles around the origin with an occasional huge one, and a point
+fn circles() -> Vec<(Vec2, f32)> {
+    (0..500)
+        .map(|i| {
+            let t = i as f32;
+            let pos = glam::vec2((t * 37.7) % 500.0 - 250.0, (t * 91.3) % 500.0 - 250.0);
+            let radius = if i % 60 == 0 {
+                70.0
+            } else {
+                1.0 + (t * 0.61) % 3.0
+            };
+            (pos, radius)
+        })
+        // a point, which a query of no size at its position has to find
+        .chain([(glam::vec2(123.0, -45.0), 0.0)])
+        .collect()
+}
+
+fn assert_finds_every_overlap(mut broad_phase: impl BroadPhase<usize>) {
+    let circles = circles();
+
+    for (index, (pos, radius)) in circles.iter().enumerate() {
+        broad_phase.insert(*pos, *radius, index);
+    }
+
+    // including queries far bigger than anything inserted
+    let queries = circles.iter().copied().chain([
+        (Vec2::ZERO, 1000.0),
+        (glam::vec2(-240.0, 10.0), 150.0),
+        (Vec2::ZERO, 1e10),
+    ]);
+
+    for (pos, radius) in queries {
+        let mut found = broad_phase.query(pos, radius);
+        found.sort_unstable();
+
+        let expected = circles
+            .iter()
+            .enumerate()
+            .filter(|(_, (other, other_radius))| {
+                cc_intersection(pos, radius, *other, *other_radius)
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        assert_eq!(found, expected, "circle at {pos} of radius {radius}");
+    }
+}
+
+#[test]
+fn quadtree_finds_every_overlap() {
+    assert_finds_every_overlap(QuadTree::new(
+        4,
+        Rect {
+            left: -250.0,
+            top: -250.0,
+            width: 501.0,
+            height: 501.0,
+        },
+    ));
+}
+
+#[test]
+fn grid_finds_every_overlap() {
+    // too few buckets to begin with, so the grid has to grow
+    assert_finds_every_overlap(SpatialGrid::new(8.0, 1));
+    assert_finds_every_overlap(SpatialGrid::new(0.5, 1000));
+}
+
+#[test]
+fn grid_handles_particles_without_a_radius() {
+    let mut simulation = Simulation::new(glam::vec2(200.0, 200.0), 0);
+    simulation.resources.insert(BroadPhaseKind::Grid);
+
+    for i in 0..10 {
+        let x = 20.0 + i as f32 * 15.0;
+        simulation.spawn([x, 100.0, 0.0, 1.0, 1.0, 1.0], glam::vec2(0.0, 50.0), 1.0);
+    }
+
+    for _ in 0..120 {
+        simulation.step(1.0 / 120.0);
+    }
+
+    assert_eq!(simulation.len(), 10);
+}
+
+#[test]
+fn grid_is_selectable_from_a_scene() {
+    let source = r#"
+        size = [200, 200]
+        broad_phase = "grid"
+
+        [[lattices]]
+        origin = [20, 20]
+        columns = 8
+        rows = 8
+        spacing = [20, 20]
+        radius = 5
+        velocity_jitter = 100
+    "#;
+
+    let mut simulation = Scene::parse(source).unwrap().into_simulation();
+
+    for _ in 0..600 {
+        simulation.step(1.0 / 120.0);
+    }
+
+    assert_eq!(simulation.instances().len(), 64);
+
+    let error = Scene::parse("size = [200, 200]\nbroad_phase = \"octree\"")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("unknown broad phase `octree`"), "{error}");
+}
@@ -0,0 +1,112 @@
+use glam::Vec2;
+use legion::IntoQuery;
+use particle_simulator::colliders::{Collider, ColliderShape};
+use particle_simulator::components::Velocity;
+use particle_simulator::Simulation;
+
+const RADIUS: f32 = 5.0;
+const SPEED: f32 = 300.0;
+
+/// Drops a particle straight onto `corner` from along `normal`, the direction
+/// the collider's surface faces there, and checks the whole flight
+fn drop_onto(shape: ColliderShape, corner: Vec2, normal: Vec2) {
+    let normal = normal.normalize();
+
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), 0);
+    simulation.add_collider(Collider::new(shape.clone()));
+
+    let start = corner + normal * (RADIUS + 40.0);
+    simulation.spawn(
+        [start.x, start.y, RADIUS, 1.0, 1.0, 1.0],
+        -normal * SPEED,
+        1.0,
+    );
+
+    for step in 0..60 {
+        simulation.step(1.0 / 120.0);
+
+        let [x, y, ..] = *simulation.instances().get(0);
+        let pos = glam::vec2(x, y);
+
+        // on the outside of the corner at every step, never past the endpoint
+        assert!(
+            (pos - corner).dot(normal) > RADIUS * 0.5,
+            "tunnelled through at step {step}: {pos}"
+        );
+
+        if let Some(contact) = shape.contact(pos, RADIUS) {
+            assert!(
+                contact.depth < 1e-3,
+                "overlapping by {} at step {step}",
+                contact.depth
+            );
+        }
+    }
+
+    let vel = <&Velocity>::query()
+        .iter(&simulation.world)
+        .map(|Velocity(vel)| *vel)
+        .next()
+        .unwrap();
+
+    // head-on at the corner, so it comes straight back out along the normal
+    assert!(
+        vel.normalize().dot(normal) > 0.999,
+        "bounced off along {vel}, expected {normal}"
+    );
+    assert!((vel.length() - SPEED).abs() < SPEED * 1e-3);
+}
+
+#[test]
+fn bounces_off_segment_endpoint() {
+    drop_onto(
+        ColliderShape::Segment {
+            a: glam::vec2(100.0, 200.0),
+            b: glam::vec2(200.0, 200.0),
+        },
+        glam::vec2(200.0, 200.0),
+        glam::vec2(1.0, -1.0),
+    );
+}
+
+#[test]
+fn bounces_off_box_corner() {
+    drop_onto(
+        ColliderShape::Aabb {
+            min: glam::vec2(150.0, 150.0),
+            max: glam::vec2(200.0, 200.0),
+        },
+        glam::vec2(200.0, 200.0),
+        glam::vec2(1.0, 1.0),
+    );
+}
+
+#[test]
+fn bounces_off_circle_diagonally() {
+    let centre = glam::vec2(200.0, 200.0);
+    let normal = glam::vec2(1.0, 1.0).normalize();
+
+    drop_onto(
+        ColliderShape::Circle {
+            centre,
+            radius: 30.0,
+        },
+        centre + normal * 30.0,
+        normal,
+    );
+}
+
+#[test]
+fn bounces_off_polygon_vertex() {
+    // the diagonal lies strictly between the normals of the edges meeting at (250, 230)
+    drop_onto(
+        ColliderShape::Polygon(vec![
+            glam::vec2(150.0, 150.0),
+            glam::vec2(250.0, 150.0),
+            glam::vec2(250.0, 230.0),
+            glam::vec2(180.0, 250.0),
+        ]),
+        glam::vec2(250.0, 230.0),
+        glam::vec2(1.0, 1.0),
+    );
+}
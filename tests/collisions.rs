@@ -0,0 +1,115 @@
+use glam::Vec2;
+use legion::{EntityStore, IntoQuery};
+use particle_simulator::boundary::{Boundary, Domain};
+use particle_simulator::components::{Mass, Velocity};
+use particle_simulator::Simulation;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Total momentum and kinetic energy of every particle, and the sum of the
+/// magnitudes of their momenta to compare errors against
+fn totals(simulation: &Simulation) -> (Vec2, f32, f32) {
+    <(&Velocity, &Mass)>::query().iter(&simulation.world).fold(
+        (Vec2::ZERO, 0.0, 0.0),
+        |(momentum, energy, scale), (Velocity(vel), Mass(mass))| {
+            (
+                momentum + *mass * *vel,
+                energy + 0.5 * mass * vel.length_squared(),
+                scale + mass * vel.length(),
+            )
+        },
+    )
+}
+
+/// Velocities of every particle, to tell whether any collided
+fn velocities(simulation: &Simulation) -> Vec<Vec2> {
+    <&Velocity>::query()
+        .iter(&simulation.world)
+        .map(|Velocity(vel)| *vel)
+        .collect()
+}
+
+/// Steps a simulation of perfectly elastic particles, far enough from the walls
+/// to never reach them, checking momentum and energy after every step
+fn assert_conserved(mut simulation: Simulation, steps: usize) {
+    let (momentum, energy, scale) = totals(&simulation);
+    let before = velocities(&simulation);
+
+    for step in 0..steps {
+        simulation.step(1.0 / 120.0);
+
+        let (new_momentum, new_energy, _) = totals(&simulation);
+        assert!(
+            (new_momentum - momentum).length() < 1e-5 * scale,
+            "momentum changed from {momentum} to {new_momentum} in step {step}"
+        );
+        assert!(
+            (new_energy - energy).abs() < 1e-4 * energy,
+            "kinetic energy changed from {energy} to {new_energy} in step {step}"
+        );
+    }
+
+    assert_ne!(velocities(&simulation), before, "nothing collided");
+}
+
+#[test]
+fn two_body_collision_conserves_momentum_and_energy() {
+    let mut simulation = Simulation::new(glam::vec2(1000.0, 1000.0), 0);
+
+    // a light particle hits a heavy one off centre
+    simulation.spawn(
+        [450.0, 500.0, 5.0, 1.0, 1.0, 1.0],
+        glam::vec2(60.0, 0.0),
+        1.0,
+    );
+    simulation.spawn(
+        [520.0, 504.0, 10.0, 1.0, 1.0, 1.0],
+        glam::vec2(-20.0, 5.0),
+        4.0,
+    );
+
+    assert_conserved(simulation, 120);
+}
+
+#[test]
+fn n_body_collisions_conserve_momentum_and_energy() {
+    let mut rng = ChaCha8Rng::seed_from_u64(4);
+    let mut simulation = Simulation::new(glam::vec2(10_000.0, 10_000.0), 0);
+
+    // a loose gas of mixed sizes in the middle of a huge domain
+    for row in 0..10 {
+        for column in 0..10 {
+            let radius = rng.random_range(2.0..6.0);
+            let pos = glam::vec2(4900.0, 4900.0) + glam::vec2(column as f32, row as f32) * 15.0;
+            let vel = glam::vec2(rng.random_range(-40.0..40.0), rng.random_range(-40.0..40.0));
+
+            simulation.spawn([pos.x, pos.y, radius, 1.0, 1.0, 1.0], vel, radius * radius);
+        }
+    }
+
+    assert_conserved(simulation, 240);
+}
+
+#[test]
+fn particles_far_from_the_origin_still_collide() {
+    let mut simulation = Simulation::new(glam::vec2(1000.0, 1000.0), 0);
+    simulation.set_domain(Domain::with_boundary(
+        glam::vec2(1000.0, 1000.0),
+        Boundary::Open,
+    ));
+
+    // floats are 8 apart out here, more than the root's fixed padding
+    let left = simulation.spawn([1e8, 0.0, 10.0, 1.0, 1.0, 1.0], glam::vec2(10.0, 0.0), 1.0);
+    simulation.spawn(
+        [1e8 + 16.0, 0.0, 10.0, 1.0, 1.0, 1.0],
+        glam::vec2(-10.0, 0.0),
+        1.0,
+    );
+
+    simulation.step(1.0 / 120.0);
+
+    // an elastic head on collision swaps the velocities
+    let entry = simulation.world.entry_ref(left).unwrap();
+    let Velocity(vel) = entry.get_component::<Velocity>().unwrap();
+    assert_eq!(*vel, glam::vec2(-10.0, 0.0), "the overlap wasn't found");
+}
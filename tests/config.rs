@@ -0,0 +1,146 @@
+use particle_simulator::broad_phase::BroadPhaseKind;
+use particle_simulator::config::Config;
+
+fn parse(args: &[&str]) -> Result<Config, String> {
+    Config::from_args(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn no_arguments_give_the_defaults() {
+    let config = parse(&[]).unwrap();
+
+    assert_eq!(config.circle_points, 8);
+    assert_eq!(config.timestep, 1.0 / 120.0);
+    assert_eq!(config.lifetime, None);
+    assert_eq!(config.eraser_radius, 30.0);
+    assert_eq!(config.gravity, 500.0);
+    assert_eq!(config.snapshot, "snapshot.bin");
+    assert!(!config.headless);
+}
+
+#[test]
+fn every_option_is_parsed() {
+    let config = parse(&[
+        "--scene",
+        "scenes/pool.toml",
+        "--seed",
+        "42",
+        "--width",
+        "640",
+        "--height",
+        "480",
+        "--vsync",
+        "--circle-points",
+        "16",
+        "--buffer-capacity",
+        "1000",
+        "--broad-phase",
+        "grid",
+        "--quad-capacity",
+        "4",
+        "--radius",
+        "2.5",
+        "--rate",
+        "100",
+        "--lifetime",
+        "3",
+        "--eraser-radius",
+        "12",
+        "--gravity",
+        "-9.8",
+        "--snapshot",
+        "state.ron",
+        "--timestep",
+        "0.01",
+        "--max-substeps",
+        "2",
+        "--headless",
+        "--steps",
+        "50",
+    ])
+    .unwrap();
+
+    assert_eq!(config.scene.as_deref(), Some("scenes/pool.toml"));
+    assert_eq!(config.seed, Some(42));
+    assert_eq!((config.width, config.height), (Some(640), Some(480)));
+    assert!(config.vsync);
+    assert_eq!(config.circle_points, 16);
+    assert_eq!(config.buffer_capacity, 1000);
+    assert_eq!(config.broad_phase, Some(BroadPhaseKind::Grid));
+    assert_eq!(config.quad_capacity, Some(4));
+    assert_eq!((config.radius, config.rate), (2.5, 100.0));
+    assert_eq!(config.lifetime, Some(3.0));
+    assert_eq!(config.eraser_radius, 12.0);
+    assert_eq!(config.gravity, -9.8);
+    assert_eq!(config.snapshot, "state.ron");
+    assert_eq!((config.timestep, config.max_substeps), (0.01, 2));
+    assert!(config.headless);
+    assert_eq!(config.steps, Some(50));
+
+    assert_eq!(
+        parse(&["--lifetime", "3", "--lifetime", "none"])
+            .unwrap()
+            .lifetime,
+        None
+    );
+}
+
+#[test]
+fn bad_arguments_are_explained() {
+    let cases: &[(&[&str], &str)] = &[
+        (
+            &["--width", "0"],
+            "`--width` and `--height` must be at least 1",
+        ),
+        (
+            &["--height", "0"],
+            "`--width` and `--height` must be at least 1",
+        ),
+        (
+            &["--circle-points", "2"],
+            "`--circle-points` must be at least 3",
+        ),
+        (
+            &["--buffer-capacity", "0"],
+            "`--buffer-capacity` must be at least 1",
+        ),
+        (
+            &["--quad-capacity", "0"],
+            "`--quad-capacity` must be at least 1",
+        ),
+        (&["--radius", "0"], "`--radius` must be positive"),
+        (&["--rate", "-1"], "`--rate` can't be negative"),
+        (
+            &["--lifetime", "0"],
+            "`--lifetime` must be positive or `none`",
+        ),
+        (
+            &["--eraser-radius", "-5"],
+            "`--eraser-radius` must be positive",
+        ),
+        (&["--gravity", "inf"], "`--gravity` must be finite"),
+        (&["--timestep", "-1"], "`--timestep` must be positive"),
+        (&["--timestep", "NaN"], "`--timestep` must be positive"),
+        (
+            &["--max-substeps", "0"],
+            "`--max-substeps` must be at least 1",
+        ),
+        (
+            &["--steps", "10"],
+            "`--steps` only applies to `--headless` runs",
+        ),
+        (&["--width", "wide"], "invalid value `wide` for `--width`"),
+        (
+            &["--lifetime", "forever"],
+            "invalid value `forever` for `--lifetime`",
+        ),
+        (&["--seed"], "missing value for `--seed`"),
+        (&["--fullscreen"], "unknown argument `--fullscreen`"),
+    ];
+
+    for (args, message) in cases {
+        assert_eq!(parse(args).unwrap_err(), *message, "{args:?}");
+    }
+
+    assert!(parse(&["--broad-phase", "octree"]).is_err());
+}
@@ -0,0 +1,76 @@
+use legion::{Entity, EntityStore};
+use particle_simulator::components::EntityIndex;
+use particle_simulator::{Simulation, FLOATS_PER_INSTANCE};
+
+const COUNT: usize = 5;
+
+/// Distinct x, y, radius and color for every particle so a mixed up slot shows
+fn instance(n: usize) -> [f32; FLOATS_PER_INSTANCE] {
+    let n = n as f32;
+    [10.0 + n, 20.0 + n, 1.0 + n, 0.1 * n, 0.2 * n, 0.3 * n]
+}
+
+/// Spawns `COUNT` resting particles, returned with the instance each one was given
+fn spawn_all() -> (Simulation, Vec<(Entity, [f32; FLOATS_PER_INSTANCE])>) {
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), 0);
+
+    let particles = (0..COUNT)
+        .map(|n| {
+            let entity = simulation.spawn(instance(n), glam::Vec2::ZERO, 1.0);
+            (entity, instance(n))
+        })
+        .collect();
+
+    (simulation, particles)
+}
+
+fn slot(simulation: &Simulation, entity: Entity) -> usize {
+    simulation
+        .world
+        .entry_ref(entity)
+        .unwrap()
+        .get_component::<EntityIndex>()
+        .unwrap()
+        .0
+}
+
+/// Despawns the particle in slot `index` and checks the instances left behind
+fn despawn_slot(index: usize) {
+    let (mut simulation, mut particles) = spawn_all();
+    let (despawned, _) = particles.remove(index);
+    let last = particles.last().unwrap().0;
+
+    assert!(simulation.despawn(despawned));
+    assert_eq!(simulation.instances().len(), COUNT - 1);
+
+    for (entity, expected) in &particles {
+        let slot = slot(&simulation, *entity);
+
+        assert_eq!(simulation.instances().get(slot), expected);
+        assert_eq!(simulation.instances().owner(slot), *entity);
+    }
+
+    // the last particle fills the gap, unless it was the one removed
+    if index < COUNT - 1 {
+        assert_eq!(slot(&simulation, last), index);
+    }
+
+    assert!(simulation.world.entry_ref(despawned).is_err());
+    assert!(!simulation.despawn(despawned));
+    assert_eq!(simulation.instances().len(), COUNT - 1);
+}
+
+#[test]
+fn despawning_the_first_particle_moves_the_last_into_its_slot() {
+    despawn_slot(0);
+}
+
+#[test]
+fn despawning_a_middle_particle_moves_the_last_into_its_slot() {
+    despawn_slot(COUNT / 2);
+}
+
+#[test]
+fn despawning_the_last_particle_leaves_the_others_in_place() {
+    despawn_slot(COUNT - 1);
+}
@@ -0,0 +1,56 @@
+use legion::IntoQuery;
+use particle_simulator::components::{EntityIndex, Velocity};
+use particle_simulator::emitter::{Emitter, SpawnShape};
+use particle_simulator::forces::{ForceField, ForceFields};
+use particle_simulator::Simulation;
+
+/// Bits of every instance float followed by every velocity in instance order,
+/// after `steps` steps of a fountain emitting randomly sized particles into a box
+fn run(seed: u64, steps: usize) -> Vec<u32> {
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), seed);
+    simulation
+        .resources
+        .insert(ForceFields(vec![ForceField::Gravity(glam::vec2(
+            0.0, 300.0,
+        ))]));
+
+    let mut emitter = Emitter::new(glam::vec2(200.0, 300.0));
+    emitter.shape = SpawnShape::Circle { radius: 20.0 };
+    emitter.direction = -std::f32::consts::FRAC_PI_2;
+    emitter.spread = 1.0;
+    emitter.speed.max = 300.0;
+    simulation.add_emitter(emitter);
+
+    for _ in 0..steps {
+        simulation.step(1.0 / 120.0);
+    }
+
+    let mut velocities = <(&EntityIndex, &Velocity)>::query()
+        .iter(&simulation.world)
+        .map(|(EntityIndex(index), Velocity(vel))| (*index, *vel))
+        .collect::<Vec<_>>();
+    velocities.sort_by_key(|(index, _)| *index);
+
+    let instances = simulation.instances();
+
+    instances
+        .as_slice()
+        .iter()
+        .copied()
+        .chain(velocities.into_iter().flat_map(|(_, vel)| vel.to_array()))
+        .map(f32::to_bits)
+        .collect()
+}
+
+#[test]
+fn same_seed_gives_bit_identical_runs() {
+    let first = run(42, 300);
+
+    assert!(!first.is_empty());
+    assert_eq!(first, run(42, 300));
+}
+
+#[test]
+fn different_seeds_diverge() {
+    assert_ne!(run(1, 300), run(2, 300));
+}
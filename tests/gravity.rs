@@ -0,0 +1,179 @@
+use glam::Vec2;
+use legion::{EntityStore, IntoQuery};
+use particle_simulator::boundary::{Boundary, Domain};
+use particle_simulator::components::Velocity;
+use particle_simulator::forces::Gravitation;
+use particle_simulator::quadtree::{QuadTree, Rect};
+use particle_simulator::{Scene, Simulation};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const SOFTENING: f32 = 1.0;
+
+/// (Position, Mass), a dense clump inside a sparse disc
+fn bodies() -> Vec<(Vec2, f32)> {
+    let mut rng = ChaCha8Rng::seed_from_u64(9);
+
+    (0..2000)
+        .map(|i| {
+            let spread = if i % 4 == 0 { 20.0 } else { 400.0 };
+            let angle = rng.random_range(0.0..std::f32::consts::TAU);
+            let distance = spread * rng.random_range(0.0f32..1.0).sqrt();
+
+            (
+                Vec2::from_angle(angle) * distance,
+                rng.random_range(1.0..10.0),
+            )
+        })
+        .collect()
+}
+
+/// Direct O(n²) sum of the pull of every other body
+fn brute_force(bodies: &[(Vec2, f32)], pos: Vec2) -> Vec2 {
+    bodies
+        .iter()
+        .map(|(other, mass)| {
+            let delta = *other - pos;
+            let dist_sq = delta.length_squared() + SOFTENING * SOFTENING;
+            delta * (mass / (dist_sq * dist_sq.sqrt()))
+        })
+        .sum()
+}
+
+/// Root mean square of the error of every body's acceleration, relative to the
+/// root mean square of the accelerations
+fn relative_error(theta: f32) -> f32 {
+    let bodies = bodies();
+    let mut tree = QuadTree::new(
+        8,
+        Rect {
+            left: -401.0,
+            top: -401.0,
+            width: 802.0,
+            height: 802.0,
+        },
+    );
+
+    for (index, (pos, _)) in bodies.iter().enumerate() {
+        tree.push((*pos, 0.0, index));
+    }
+
+    let mass_of = |index: &usize| bodies[*index].1;
+    tree.compute_mass_distribution(&mass_of);
+
+    let (error, total) = bodies.iter().fold((0.0, 0.0), |(error, total), (pos, _)| {
+        let exact = brute_force(&bodies, *pos);
+        let approximate = tree.barnes_hut_acceleration(*pos, theta, SOFTENING, &mass_of);
+
+        (
+            error + (approximate - exact).length_squared(),
+            total + exact.length_squared(),
+        )
+    });
+
+    (error / total).sqrt()
+}
+
+#[test]
+fn barnes_hut_matches_brute_force() {
+    // opening every node sums the same pulls, only in a different order
+    assert!(relative_error(0.0) < 1e-4);
+
+    assert!(relative_error(0.3) < 0.01);
+    assert!(relative_error(0.7) < 0.05);
+}
+
+#[test]
+fn scenes_set_mutual_gravity() {
+    let simulation = Scene::parse(
+        r#"
+        size = [100, 100]
+
+        [gravitation]
+        constant = 20
+        theta = 0.8
+        softening = 3
+        "#,
+    )
+    .unwrap()
+    .into_simulation();
+
+    assert_eq!(
+        *simulation.resources.get::<Gravitation>().unwrap(),
+        Gravitation {
+            constant: 20.0,
+            theta: 0.8,
+            softening: 3.0,
+        }
+    );
+}
+
+#[test]
+fn two_bodies_pull_each_other_with_equal_and_opposite_momentum() {
+    let gravitation = Gravitation {
+        constant: 1000.0,
+        ..Default::default()
+    };
+
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), 0);
+    simulation.resources.insert(gravitation);
+
+    let (m1, m2) = (2.0, 6.0);
+    let a = simulation.spawn([150.0, 200.0, 2.0, 1.0, 1.0, 1.0], Vec2::ZERO, m1);
+    let b = simulation.spawn([250.0, 200.0, 2.0, 1.0, 1.0, 1.0], Vec2::ZERO, m2);
+
+    let velocity = |simulation: &Simulation, entity| {
+        simulation
+            .world
+            .entry_ref(entity)
+            .unwrap()
+            .get_component::<Velocity>()
+            .unwrap()
+            .0
+    };
+
+    let dt = 1.0 / 120.0;
+    simulation.step(dt);
+
+    let (p1, p2) = (m1 * velocity(&simulation, a), m2 * velocity(&simulation, b));
+
+    // towards each other along the line between them
+    assert!(p1.x > 0.0 && p2.x < 0.0, "{p1} {p2}");
+    assert_eq!((p1.y, p2.y), (0.0, 0.0));
+    assert!((p1 + p2).length() < p1.length() * 1e-4, "{p1} {p2}");
+
+    // G m1 m2 / r^2 over one step, with the softening added to the distance
+    let distance_sq = 100.0f32 * 100.0 + gravitation.softening * gravitation.softening;
+    let expected = gravitation.constant * m1 * m2 * 100.0 / (distance_sq * distance_sq.sqrt()) * dt;
+    assert!(
+        (p1.x - expected).abs() < expected * 1e-2,
+        "momentum change {}, expected {expected}",
+        p1.x
+    );
+}
+
+#[test]
+fn bodies_far_from_the_origin_still_attract() {
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), 0);
+    simulation.set_domain(Domain::with_boundary(
+        glam::vec2(400.0, 400.0),
+        Boundary::Open,
+    ));
+    simulation.resources.insert(Gravitation {
+        constant: 1000.0,
+        ..Default::default()
+    });
+
+    // floats are 8 apart out here, more than the root's fixed padding
+    for x in [1e8, 1e8 + 64.0] {
+        simulation.spawn([x, 0.0, 2.0, 1.0, 1.0, 1.0], Vec2::ZERO, 1.0);
+    }
+
+    simulation.step(1.0 / 120.0);
+
+    let velocities = <&Velocity>::query()
+        .iter(&simulation.world)
+        .map(|Velocity(vel)| vel.x)
+        .collect::<Vec<_>>();
+    assert!(velocities[0] > 0.0 && velocities[1] < 0.0, "{velocities:?}");
+}
@@ -0,0 +1,101 @@
+use glam::Vec2;
+use legion::IntoQuery;
+use particle_simulator::components::Velocity;
+use particle_simulator::forces::{ForceField, ForceFields};
+use particle_simulator::{Integrator, Simulation};
+
+/// Largest relative change of `energy` over `steps` steps from `pos` and `vel`
+fn energy_drift(
+    integrator: Integrator,
+    (mut pos, mut vel): (Vec2, Vec2),
+    steps: usize,
+    dt: f32,
+    accel: impl Fn(Vec2, Vec2) -> Vec2,
+    energy: impl Fn(Vec2, Vec2) -> f32,
+) -> f32 {
+    let initial = energy(pos, vel);
+    let mut worst: f32 = 0.0;
+
+    for _ in 0..steps {
+        (pos, vel) = integrator.step(pos, vel, dt, &accel);
+        worst = worst.max(((energy(pos, vel) - initial) / initial).abs());
+    }
+
+    worst
+}
+
+fn assert_drifts_less_than_euler(drift: impl Fn(Integrator) -> f32) {
+    let euler = drift(Integrator::ExplicitEuler);
+
+    assert!(drift(Integrator::SemiImplicitEuler) < euler);
+    assert!(drift(Integrator::VelocityVerlet) < euler * 0.01);
+    assert!(drift(Integrator::Rk4) < euler * 0.01);
+}
+
+#[test]
+fn harmonic_oscillator_energy_drift() {
+    // a unit mass on a spring with an angular frequency of 2, about 30 periods
+    let stiffness = 4.0;
+
+    assert_drifts_less_than_euler(|integrator| {
+        energy_drift(
+            integrator,
+            (glam::vec2(1.0, 0.0), Vec2::ZERO),
+            10_000,
+            0.01,
+            |pos, _| -stiffness * pos,
+            |pos, vel| 0.5 * vel.length_squared() + 0.5 * stiffness * pos.length_squared(),
+        )
+    });
+}
+
+#[test]
+fn circular_orbit_energy_drift() {
+    // a unit radius orbit around a unit mass, about 8 revolutions
+    assert_drifts_less_than_euler(|integrator| {
+        energy_drift(
+            integrator,
+            (glam::vec2(1.0, 0.0), glam::vec2(0.0, 1.0)),
+            5_000,
+            0.01,
+            |pos, _| -pos / pos.length().powi(3),
+            |pos, vel| 0.5 * vel.length_squared() - 1.0 / pos.length(),
+        )
+    });
+}
+
+/// Relative error of the speed of a particle slowed by linear drag, against the
+/// exact exponential decay
+fn drag_error(integrator: Integrator) -> f32 {
+    let (drag, mass, speed) = (2.0, 1.0, 10.0);
+    let (steps, dt) = (20, 0.05);
+
+    let mut simulation = Simulation::new(glam::vec2(1000.0, 1000.0), 0);
+    simulation.resources.insert(integrator);
+    simulation
+        .resources
+        .insert(ForceFields(vec![ForceField::LinearDrag(drag)]));
+    simulation.spawn(
+        [500.0, 500.0, 1.0, 1.0, 1.0, 1.0],
+        glam::vec2(speed, 0.0),
+        mass,
+    );
+
+    for _ in 0..steps {
+        simulation.step(dt);
+    }
+
+    let Velocity(vel) = *<&Velocity>::query().iter(&simulation.world).next().unwrap();
+    let exact = speed * (-drag / mass * steps as f32 * dt).exp();
+
+    (vel.x - exact).abs() / exact
+}
+
+#[test]
+fn drag_is_evaluated_at_every_stage() {
+    let euler = drag_error(Integrator::ExplicitEuler);
+
+    // holding the drag constant over a step would make these as inaccurate as Euler
+    assert!(drag_error(Integrator::VelocityVerlet) < euler * 0.5);
+    assert!(drag_error(Integrator::Rk4) < euler * 0.01);
+}
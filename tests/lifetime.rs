@@ -0,0 +1,112 @@
+use particle_simulator::{Scene, Simulation};
+
+fn scene(particles: &str) -> Simulation {
+    Scene::parse(&format!("size = [200, 200]\n{particles}"))
+        .unwrap()
+        .into_simulation()
+}
+
+/// Radius and color of every particle
+fn looks(simulation: &Simulation) -> Vec<[f32; 4]> {
+    let instances = simulation.instances();
+
+    (0..instances.len())
+        .map(|index| {
+            let [_, _, radius, red, green, blue] = *instances.get(index);
+            [radius, red, green, blue]
+        })
+        .collect()
+}
+
+fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+    for (a, e) in actual.into_iter().zip(expected) {
+        assert!((a - e).abs() < 1e-3, "{actual:?} isn't {expected:?}");
+    }
+}
+
+#[test]
+fn color_and_radius_interpolate_over_the_lifetime() {
+    let mut simulation = scene(
+        r#"
+        [[particles]]
+        position = [50, 100]
+        radius = 10
+        color = [1, 0.5, 0]
+        lifetime = 1
+        fade_color = [0, 0.5, 1]
+
+        [[particles]]
+        position = [150, 100]
+        radius = 8
+        color = [1, 1, 1]
+        lifetime = 2
+        "#,
+    );
+
+    for (steps, t) in [(25, 0.25), (25, 0.5), (25, 0.75)] {
+        for _ in 0..steps {
+            simulation.step(0.01);
+        }
+
+        // the second particle lives twice as long and fades to black
+        let looks = looks(&simulation);
+        assert_close(looks[0], [10.0 * (1.0 - t), 1.0 - t, 0.5, t]);
+        assert_close(
+            looks[1],
+            [
+                8.0 * (1.0 - t / 2.0),
+                1.0 - t / 2.0,
+                1.0 - t / 2.0,
+                1.0 - t / 2.0,
+            ],
+        );
+    }
+
+    for _ in 0..26 {
+        simulation.step(0.01);
+    }
+
+    assert_eq!(simulation.len(), 1, "the first particle has expired");
+}
+
+#[test]
+fn fade_color_components_are_checked() {
+    let errors = Scene::parse(
+        r#"
+        size = [200, 200]
+
+        [[particles]]
+        position = [50, 100]
+        radius = 10
+        lifetime = 1
+        fade_color = [0, 2, 0]
+        "#,
+    )
+    .unwrap_err();
+
+    assert!(errors.to_string().contains("`fade_color`"), "{errors}");
+}
+
+#[test]
+fn emitted_particles_fade_to_the_emitter_color() {
+    let mut simulation = scene(
+        r#"
+        [[emitters]]
+        position = [100, 100]
+        burst = { count = 1 }
+        speed = 0
+        radius = 4
+        color = [1, 1, 1]
+        lifetime = 1
+        fade_color = [1, 0, 0]
+        "#,
+    );
+
+    // emitted particles only join the world at the end of the step they appear in,
+    // so they start aging in the next one
+    for _ in 0..51 {
+        simulation.step(0.01);
+    }
+
+    assert_close(looks(&simulation)[0], [2.0, 1.0, 0.5, 0.5]);
+}
@@ -0,0 +1,169 @@
+use std::f32::consts::PI;
+
+use glam::Vec2;
+use legion::IntoQuery;
+use particle_simulator::components::{EntityIndex, Mass};
+use particle_simulator::material::Material;
+use particle_simulator::{Scene, Simulation};
+
+/// (Radius, Mass) of every particle
+fn radii_and_masses(simulation: &Simulation) -> Vec<(f32, f32)> {
+    let instances = simulation.instances();
+
+    <(&EntityIndex, &Mass)>::query()
+        .iter(&simulation.world)
+        .map(|(EntityIndex(index), Mass(mass))| (instances.get(*index)[2], *mass))
+        .collect()
+}
+
+#[test]
+fn mass_is_density_times_area() {
+    assert_eq!(Material::default().mass(2.0), 4.0 * PI);
+
+    let mut simulation = Scene::parse(
+        r#"
+        size = [400, 400]
+        seed = 1
+
+        [[materials]]
+        name = "lead"
+        density = 11.3
+
+        [[particles]]
+        position = [50, 50]
+        radius = 3
+        material = "lead"
+
+        [[particles]]
+        position = [100, 50]
+        radius = 3
+        material = "lead"
+        mass = 1
+
+        [[lattices]]
+        origin = [50, 100]
+        columns = 5
+        rows = 1
+        spacing = [30, 30]
+        radius = [2, 8]
+        material = "lead"
+
+        [[emitters]]
+        position = [200, 300]
+        burst = { count = 20 }
+        speed = 0
+        radius = [1, 5]
+        material = "lead"
+        "#,
+    )
+    .unwrap()
+    .into_simulation();
+
+    simulation.step(1.0 / 120.0);
+
+    let particles = radii_and_masses(&simulation);
+    assert_eq!(particles.len(), 27);
+
+    // only the particle with an explicit mass differs
+    let explicit = particles
+        .iter()
+        .filter(|(radius, mass)| (mass - 11.3 * PI * radius * radius).abs() > mass * 1e-6)
+        .collect::<Vec<_>>();
+    assert_eq!(explicit, [&(3.0, 1.0)]);
+}
+
+/// Root mean square distance of the particles from their centre
+fn spread(simulation: &Simulation) -> f32 {
+    let instances = simulation.instances();
+    let positions = (0..instances.len())
+        .map(|index| Vec2::from_slice(instances.get(index)))
+        .collect::<Vec<_>>();
+
+    let centre = positions.iter().sum::<Vec2>() / positions.len() as f32;
+    let mean_sq = positions
+        .iter()
+        .map(|pos| pos.distance_squared(centre))
+        .sum::<f32>()
+        / positions.len() as f32;
+
+    mean_sq.sqrt()
+}
+
+/// Spread of a jittery block of water-like particles before and after two seconds
+fn spread_with_cohesion(cohesion: f32) -> (f32, f32) {
+    let source = format!(
+        r#"
+        size = [1000, 1000]
+        seed = 3
+
+        [[materials]]
+        name = "goo"
+        restitution = 0.1
+        cohesion = {cohesion}
+
+        [[lattices]]
+        origin = [440, 440]
+        columns = 6
+        rows = 6
+        spacing = [22, 22]
+        velocity_jitter = 40
+        radius = 10
+        material = "goo"
+        "#
+    );
+
+    let mut simulation = Scene::parse(&source).unwrap().into_simulation();
+    let before = spread(&simulation);
+
+    for _ in 0..240 {
+        simulation.step(1.0 / 120.0);
+    }
+
+    (before, spread(&simulation))
+}
+
+#[test]
+fn cohesive_particles_clump_together() {
+    let (before, loose) = spread_with_cohesion(0.0);
+    assert!(loose > before * 1.2, "spread went from {before} to {loose}");
+
+    let (before, cohesive) = spread_with_cohesion(2000.0);
+    assert!(
+        cohesive < before * 1.05,
+        "spread went from {before} to {cohesive}"
+    );
+}
+
+#[test]
+fn unknown_materials_are_rejected() {
+    let source = r#"
+size = [200, 200]
+
+[[materials]]
+name = "sand"
+
+[[particles]]
+position = [50, 50]
+radius = 5
+material = "sand"
+
+[[particles]]
+position = [100, 50]
+radius = 5
+material = "snad"
+
+[[emitters]]
+position = [100, 100]
+rate = 10
+speed = 20
+radius = 4
+material = "water"
+"#;
+
+    let errors = Scene::parse(source).unwrap_err().to_string();
+    assert_eq!(
+        errors,
+        "line 15: `material` isn't defined in `materials`\n\
+         line 22: `material` isn't defined in `materials`"
+    );
+}
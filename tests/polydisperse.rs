@@ -0,0 +1,242 @@
+use particle_simulator::emitter::{Distribution, ValueRange};
+use particle_simulator::quadtree::{cc_intersection, QuadTree, Rect};
+use particle_simulator::{Scene, Simulation};
+use rand::SeedableRng;
+
+/// Largest overlap of two particles as a fraction of the smaller radius
+fn worst_overlap(simulation: &Simulation) -> f32 {
+    let instances = simulation.instances();
+    let circles = (0..instances.len())
+        .map(|index| {
+            let [x, y, radius, ..] = *instances.get(index);
+            (glam::vec2(x, y), radius)
+        })
+        .collect::<Vec<_>>();
+
+    let mut worst: f32 = 0.0;
+    for (a, (pos_a, radius_a)) in circles.iter().enumerate() {
+        for (pos_b, radius_b) in &circles[a + 1..] {
+            let overlap = radius_a + radius_b - pos_a.distance(*pos_b);
+            worst = worst.max(overlap / radius_a.min(*radius_b));
+        }
+    }
+
+    worst
+}
+
+fn settle(source: &str, steps: usize) -> Simulation {
+    let mut simulation = Scene::parse(source).unwrap().into_simulation();
+
+    for _ in 0..steps {
+        simulation.step(1.0 / 240.0);
+    }
+
+    simulation
+}
+
+#[test]
+fn polydisperse_pile_settles_without_overlaps() {
+    let simulation = settle(
+        r#"
+        size = [400, 400]
+        seed = 5
+        contact_passes = 8
+
+        [[force_fields]]
+        gravity = [0, 500]
+
+        [[force_fields]]
+        linear_drag = 0.5
+
+        [[materials]]
+        name = "grain"
+        restitution = 0.2
+        friction = 0.3
+
+        [[lattices]]
+        origin = [20, 20]
+        columns = 18
+        rows = 8
+        spacing = [21, 21]
+        radius = { power_law = { range = [2, 10], exponent = 2.5 } }
+        material = "grain"
+        "#,
+        2400,
+    );
+
+    let domain = simulation.domain().size;
+    let instances = simulation.instances();
+
+    for index in 0..instances.len() {
+        let [x, y, radius, ..] = *instances.get(index);
+
+        assert!((2.0..=10.0).contains(&radius));
+        assert!(x >= radius - 1e-3 && x <= domain.x - radius + 1e-3);
+        assert!(y >= radius - 1e-3 && y <= domain.y - radius + 1e-3);
+    }
+
+    drop(instances);
+    let overlap = worst_overlap(&simulation);
+    assert!(
+        overlap < 0.25,
+        "particles overlap by {overlap} of their radius"
+    );
+}
+
+#[test]
+fn small_particles_rest_on_a_large_one() {
+    // a ring of pebbles dropped onto a boulder a hundred times their size
+    let simulation = settle(
+        r#"
+        size = [600, 600]
+        seed = 1
+
+        [[force_fields]]
+        gravity = [0, 300]
+
+        [[materials]]
+        name = "rock"
+        restitution = 0.1
+        friction = 0.5
+
+        [[particles]]
+        position = [300, 500]
+        radius = 100
+        material = "rock"
+
+        [[lattices]]
+        origin = [250, 300]
+        columns = 11
+        rows = 2
+        spacing = [10, 10]
+        radius = 1
+        material = "rock"
+        "#,
+        1200,
+    );
+
+    let instances = simulation.instances();
+    let [x, y, radius, ..] = *instances.get(0);
+    let boulder = glam::vec2(x, y);
+
+    for index in 1..instances.len() {
+        let [x, y, pebble, ..] = *instances.get(index);
+        let distance = boulder.distance(glam::vec2(x, y));
+
+        assert!(
+            distance >= radius + pebble - 0.5,
+            "pebble {index} sank {} into the boulder",
+            radius + pebble - distance
+        );
+    }
+}
+
+#[test]
+fn quadtree_finds_overlaps_between_any_sizes() {
+    let circles = (0..400)
+        .map(|i| {
+            let t = i as f32;
+            let pos = glam::vec2((t * 37.7) % 500.0, (t * 91.3) % 500.0);
+
+            // mostly tiny circles with an occasional huge one
+            let radius = if i % 50 == 0 {
+                80.0
+            } else {
+                0.5 + (t * 0.61) % 3.0
+            };
+            (pos, radius)
+        })
+        .collect::<Vec<_>>();
+
+    let mut tree = QuadTree::new(
+        4,
+        Rect {
+            left: 0.0,
+            top: 0.0,
+            width: 501.0,
+            height: 501.0,
+        },
+    );
+
+    for (index, (pos, radius)) in circles.iter().enumerate() {
+        tree.push((*pos, *radius, index));
+    }
+
+    for (a, (pos, radius)) in circles.iter().enumerate() {
+        // sorted but not deduplicated, every overlapping circle is found exactly once
+        let mut found = tree.query(*pos, *radius);
+        found.sort_unstable();
+
+        let expected = circles
+            .iter()
+            .enumerate()
+            .filter(|(_, (other, other_radius))| {
+                cc_intersection(*pos, *radius, *other, *other_radius)
+            })
+            .map(|(b, _)| b)
+            .collect::<Vec<_>>();
+
+        assert_eq!(found, expected, "circle {a}");
+    }
+}
+
+#[test]
+fn radius_distributions_stay_in_bounds() {
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(3);
+    let range = ValueRange::new(2.0, 10.0);
+
+    let distributions = [
+        Distribution::Uniform(range),
+        Distribution::Normal {
+            mean: 4.0,
+            std_dev: 3.0,
+            range,
+        },
+        Distribution::PowerLaw {
+            range,
+            exponent: 2.5,
+        },
+        Distribution::PowerLaw {
+            range,
+            exponent: 1.0,
+        },
+    ];
+
+    for distribution in distributions {
+        let samples = (0..10_000)
+            .map(|_| distribution.sample(&mut rng))
+            .collect::<Vec<_>>();
+
+        assert!(samples.iter().all(|r| (2.0..=10.0).contains(r)));
+    }
+
+    // most of a power law is at the small end
+    let power_law = Distribution::PowerLaw {
+        range,
+        exponent: 2.5,
+    };
+
+    let small = (0..10_000)
+        .filter(|_| power_law.sample(&mut rng) < 4.0)
+        .count();
+
+    assert!(small > 6_000, "{small}");
+
+    let log_normal = Distribution::LogNormal {
+        median: 5.0,
+        sigma: 0.5,
+    };
+
+    let mut samples = (0..10_001)
+        .map(|_| log_normal.sample(&mut rng))
+        .collect::<Vec<_>>();
+
+    samples.sort_by(f32::total_cmp);
+
+    assert!(samples[0] > 0.0);
+    assert!(
+        (samples[5_000] - 5.0).abs() < 0.2,
+        "median {}",
+        samples[5_000]
+    );
+}
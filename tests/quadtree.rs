@@ -0,0 +1,476 @@
+use glam::Vec2;
+use particle_simulator::quadtree::{QuadTree, Rect};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const BOUNDARY: Rect = Rect {
+    left: -100.0,
+    top: -100.0,
+    width: 200.0,
+    height: 200.0,
+};
+
+fn fresh(capacity: usize, points: &[(Vec2, f32, usize)]) -> QuadTree<usize> {
+    let mut tree = QuadTree::new(capacity, BOUNDARY);
+
+    for point in points {
+        tree.push(*point);
+    }
+
+    tree
+}
+
+fn sorted(mut found: Vec<usize>) -> Vec<usize> {
+    found.sort_unstable();
+    found
+}
+
+/// Positions mostly inside the boundary, some just outside it
+fn random_pos(rng: &mut impl Rng) -> Vec2 {
+    glam::vec2(
+        rng.random_range(-110.0..110.0),
+        rng.random_range(-110.0..110.0),
+    )
+}
+
+/// Applies random pushes, removals and moves to a tree kept across all of them,
+/// checking it against one built from scratch after every operation
+fn check_persistent_tree(seed: u64) {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let capacity = rng.random_range(1..6);
+
+    let mut tree = QuadTree::new(capacity, BOUNDARY);
+    let mut points: Vec<(Vec2, f32, usize)> = vec![];
+
+    for id in 0..400 {
+        let roll = rng.random_range(0..10);
+
+        if roll < 4 || points.is_empty() {
+            let pos = random_pos(&mut rng);
+            let point = (pos, rng.random_range(0.5..8.0), id);
+
+            // points outside the boundary are ignored by both
+            tree.push(point);
+            if pos.cmpge(Vec2::splat(-100.0)).all() && pos.cmplt(Vec2::splat(100.0)).all() {
+                points.push(point);
+            }
+        } else if roll < 6 {
+            let (pos, radius, data) = points.swap_remove(rng.random_range(0..points.len()));
+            assert_eq!(
+                tree.remove(pos, &data),
+                Some((pos, radius, data)),
+                "seed {seed}"
+            );
+            assert_eq!(tree.remove(pos, &data), None, "seed {seed}");
+        } else {
+            let slot = rng.random_range(0..points.len());
+            let (pos, _, data) = points[slot];
+
+            // mostly small steps, as particles move between frames
+            let new_pos = if rng.random_bool(0.8) {
+                pos + glam::vec2(rng.random_range(-5.0..5.0), rng.random_range(-5.0..5.0))
+            } else {
+                random_pos(&mut rng)
+            };
+
+            if tree.update(pos, &data, new_pos) {
+                points[slot].0 = new_pos;
+            } else {
+                points.swap_remove(slot);
+            }
+        }
+
+        assert_eq!(tree.len(), points.len(), "seed {seed}");
+
+        let reference = fresh(capacity, &points);
+        for _ in 0..4 {
+            let centre = random_pos(&mut rng);
+            let radius = rng.random_range(0.0..40.0);
+
+            assert_eq!(
+                sorted(tree.query(centre, radius)),
+                sorted(reference.query(centre, radius)),
+                "seed {seed}, query at {centre} of radius {radius}"
+            );
+        }
+    }
+}
+
+#[test]
+fn persistent_tree_answers_like_a_fresh_one() {
+    for seed in 0..64 {
+        check_persistent_tree(seed);
+    }
+}
+
+#[test]
+fn removing_everything_merges_back_to_a_leaf() {
+    let mut rng = ChaCha8Rng::seed_from_u64(1);
+    let points = (0..200)
+        .map(|id| {
+            (
+                random_pos(&mut rng).clamp(Vec2::splat(-99.0), Vec2::splat(99.0)),
+                1.0,
+                id,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut tree = fresh(4, &points);
+
+    for (pos, _, data) in &points {
+        assert!(tree.remove(*pos, data).is_some());
+    }
+
+    assert!(tree.is_empty());
+
+    // nothing is left behind that a query could still reach
+    tree.push((Vec2::ZERO, 1.0, 0));
+    assert_eq!(
+        tree.query(glam::vec2(50.0, 50.0), 10.0),
+        Vec::<usize>::new()
+    );
+    assert_eq!(tree.query(Vec2::ZERO, 0.5), vec![0]);
+}
+
+#[test]
+fn moving_out_of_the_boundary_removes_the_point() {
+    let mut tree = fresh(2, &[(Vec2::ZERO, 1.0, 7), (glam::vec2(10.0, 0.0), 1.0, 8)]);
+
+    assert!(!tree.update(Vec2::ZERO, &7, glam::vec2(500.0, 0.0)));
+    assert!(!tree.update(Vec2::ZERO, &7, glam::vec2(1.0, 0.0)));
+    assert_eq!(tree.len(), 1);
+
+    assert!(tree.update(glam::vec2(10.0, 0.0), &8, glam::vec2(-50.0, 20.0)));
+    assert_eq!(tree.query(glam::vec2(-50.0, 20.0), 0.5), vec![8]);
+}
+
+/// Points of mixed sizes with a few exact duplicates, in a tree of small nodes
+fn scattered(seed: u64) -> (QuadTree<usize>, Vec<(Vec2, f32, usize)>) {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+    let mut points = (0..300)
+        .map(|id| {
+            let pos = glam::vec2(
+                rng.random_range(-100.0..100.0),
+                rng.random_range(-100.0..100.0),
+            );
+            let radius = if id % 40 == 0 {
+                30.0
+            } else {
+                rng.random_range(0.0..4.0)
+            };
+            (pos, radius, id)
+        })
+        .collect::<Vec<_>>();
+
+    let duplicates = (300..310)
+        .map(|id| (points[id - 300].0, 1.0, id))
+        .collect::<Vec<_>>();
+    points.extend(duplicates);
+
+    (fresh(3, &points), points)
+}
+
+#[test]
+fn rect_query_matches_brute_force() {
+    for seed in 0..16 {
+        let (tree, points) = scattered(seed);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed + 100);
+
+        for _ in 0..50 {
+            let rect = Rect {
+                left: rng.random_range(-120.0..100.0),
+                top: rng.random_range(-120.0..100.0),
+                width: rng.random_range(0.0..80.0),
+                height: rng.random_range(0.0..80.0),
+            };
+
+            let expected = points
+                .iter()
+                .filter(|(pos, radius, _)| {
+                    let closest = pos.clamp(
+                        glam::vec2(rect.left, rect.top),
+                        glam::vec2(rect.left + rect.width, rect.top + rect.height),
+                    );
+                    pos.distance(closest) <= *radius
+                })
+                .map(|(.., id)| *id)
+                .collect::<Vec<_>>();
+
+            assert_eq!(sorted(tree.query_rect(&rect)), expected, "{rect:?}");
+        }
+    }
+}
+
+#[test]
+fn nearest_queries_match_brute_force() {
+    for seed in 0..16 {
+        let (tree, points) = scattered(seed);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed + 200);
+
+        for _ in 0..50 {
+            let pos = random_pos(&mut rng) * 1.5;
+            let k = rng.random_range(1..20);
+
+            let mut distances = points
+                .iter()
+                .map(|(centre, ..)| pos.distance_squared(*centre))
+                .collect::<Vec<_>>();
+            distances.sort_by(f32::total_cmp);
+
+            // ties make the ids ambiguous, so only the distances are compared
+            let distance_of = |id: &usize| pos.distance_squared(points[*id].0);
+
+            let nearest = tree.nearest(pos).unwrap();
+            assert_eq!(distance_of(&nearest), distances[0]);
+
+            let k_nearest = tree.k_nearest(pos, k);
+            assert_eq!(
+                k_nearest.iter().map(distance_of).collect::<Vec<_>>(),
+                distances[..k],
+                "{k} nearest to {pos}"
+            );
+        }
+
+        assert_eq!(tree.k_nearest(Vec2::ZERO, 0), Vec::<usize>::new());
+        assert_eq!(tree.k_nearest(Vec2::ZERO, 1000).len(), points.len());
+    }
+
+    assert_eq!(
+        QuadTree::<usize>::new(4, BOUNDARY).nearest(Vec2::ZERO),
+        None
+    );
+}
+
+#[test]
+fn visitors_see_what_queries_return() {
+    let (tree, points) = scattered(3);
+
+    let mut visited = vec![];
+    tree.for_each_in_circle(glam::vec2(10.0, -20.0), 25.0, &mut |pos, radius, id| {
+        assert_eq!(points[*id], (pos, radius, *id));
+        visited.push(*id);
+    });
+    assert_eq!(
+        sorted(visited),
+        sorted(tree.query(glam::vec2(10.0, -20.0), 25.0))
+    );
+
+    let rect = Rect {
+        left: -30.0,
+        top: 0.0,
+        width: 50.0,
+        height: 20.0,
+    };
+
+    let mut visited = vec![];
+    tree.for_each_in_rect(&rect, &mut |_, _, id| visited.push(*id));
+    assert_eq!(sorted(visited), sorted(tree.query_rect(&rect)));
+}
+
+#[test]
+fn points_sharing_a_position_stop_splitting() {
+    let mut tree = QuadTree::new(1, BOUNDARY);
+
+    for id in 0..10_000 {
+        tree.push((glam::vec2(12.5, -3.0), 1.0, id));
+    }
+
+    assert_eq!(tree.len(), 10_000);
+    assert_eq!(tree.query(glam::vec2(12.5, -3.0), 0.0).len(), 10_000);
+    assert_eq!(tree.k_nearest(Vec2::ZERO, 3).len(), 3);
+
+    assert!(tree.remove(glam::vec2(12.5, -3.0), &5_000).is_some());
+    assert_eq!(tree.len(), 9_999);
+}
+
+#[test]
+fn cleared_tree_answers_like_a_fresh_one() {
+    let (mut tree, _) = scattered(7);
+    let (_, points) = scattered(8);
+
+    let boundary = Rect {
+        left: -150.0,
+        top: -150.0,
+        width: 300.0,
+        height: 300.0,
+    };
+
+    tree.clear(boundary.clone());
+    assert!(tree.is_empty());
+
+    let mut reference = QuadTree::new(3, boundary);
+    for point in &points {
+        tree.push(*point);
+        reference.push(*point);
+    }
+
+    for (pos, radius, _) in &points {
+        assert_eq!(
+            sorted(tree.query(*pos, *radius * 3.0)),
+            sorted(reference.query(*pos, *radius * 3.0))
+        );
+    }
+}
+
+/// Circles centred on the edges and corners of the nodes at every level down to
+/// a 25 wide one, with radii from a speck to several nodes across
+fn straddlers() -> Vec<(Vec2, f32, usize)> {
+    let lines = (0..=8)
+        .map(|i| -100.0 + 25.0 * i as f32)
+        .collect::<Vec<_>>();
+
+    let mut points = vec![];
+    for x in &lines[..8] {
+        for y in &lines[..8] {
+            for (offset, radius) in [
+                (Vec2::ZERO, 0.01),
+                (glam::vec2(0.0, 12.5), 30.0),
+                (glam::vec2(12.5, 0.0), 3.0),
+            ] {
+                let id = points.len();
+                points.push((glam::vec2(*x, *y) + offset, radius, id));
+            }
+        }
+    }
+
+    points
+}
+
+fn assert_exactly_once(found: Vec<usize>, expected: &[usize], context: &str) {
+    let mut sorted_found = found.clone();
+    sorted_found.sort_unstable();
+    sorted_found.dedup();
+
+    assert_eq!(sorted_found.len(), found.len(), "duplicates for {context}");
+    assert_eq!(sorted_found, expected, "{context}");
+}
+
+#[test]
+fn straddling_circles_are_found_exactly_once() {
+    let points = straddlers();
+
+    for capacity in [1, 2, 8] {
+        let tree = fresh(capacity, &points);
+
+        let queries = points
+            .iter()
+            .map(|(pos, radius, _)| (*pos, *radius))
+            .chain((0..=8).map(|i| (glam::vec2(-100.0 + 25.0 * i as f32, 7.0), 0.0)));
+
+        for (pos, radius) in queries {
+            let expected = points
+                .iter()
+                .filter(|(other, other_radius, _)| pos.distance(*other) <= radius + other_radius)
+                .map(|(.., id)| *id)
+                .collect::<Vec<_>>();
+
+            let context = format!("capacity {capacity}, circle at {pos} of radius {radius}");
+            assert_exactly_once(tree.query(pos, radius), &expected, &context);
+
+            let mut visited = vec![];
+            tree.for_each_in_circle(pos, radius, &mut |_, _, id| visited.push(*id));
+            assert_exactly_once(visited, &expected, &context);
+        }
+
+        for (pos, _, _) in &points {
+            let rect = Rect {
+                left: pos.x - 5.0,
+                top: pos.y - 5.0,
+                width: 10.0,
+                height: 10.0,
+            };
+
+            let expected = points
+                .iter()
+                .filter(|(other, other_radius, _)| {
+                    let closest = other.clamp(*pos - 5.0, *pos + 5.0);
+                    other.distance(closest) <= *other_radius
+                })
+                .map(|(.., id)| *id)
+                .collect::<Vec<_>>();
+
+            assert_exactly_once(tree.query_rect(&rect), &expected, &format!("{rect:?}"));
+        }
+    }
+}
+
+#[test]
+fn moved_circles_are_found_exactly_once() {
+    let mut points = straddlers();
+    let mut tree = fresh(2, &points);
+    let mut rng = ChaCha8Rng::seed_from_u64(11);
+
+    // shuffle every circle onto another node edge, some of them twice
+    for _ in 0..2 {
+        for (pos, _, id) in points.iter_mut() {
+            if rng.random_bool(0.3) {
+                continue;
+            }
+
+            let new_pos = glam::vec2(
+                -100.0 + 12.5 * rng.random_range(0..16) as f32,
+                -100.0 + 12.5 * rng.random_range(0..16) as f32,
+            );
+
+            assert!(tree.update(*pos, id, new_pos));
+            *pos = new_pos;
+        }
+    }
+
+    for (pos, radius, _) in &points {
+        let expected = points
+            .iter()
+            .filter(|(other, other_radius, _)| pos.distance(*other) <= radius + other_radius)
+            .map(|(.., id)| *id)
+            .collect::<Vec<_>>();
+
+        assert_exactly_once(
+            tree.query(*pos, *radius),
+            &expected,
+            &format!("circle at {pos}"),
+        );
+    }
+}
+
+#[test]
+fn parallel_rebuild_builds_the_same_tree() {
+    let mut rng = ChaCha8Rng::seed_from_u64(11);
+
+    // enough to be split between threads a few levels down, with a clump sharing
+    // one position to reach the depth limit
+    let mut points = (0..20_000)
+        .map(|id| (random_pos(&mut rng), rng.random_range(0.0..2.0), id))
+        .collect::<Vec<_>>();
+    points.extend((20_000..20_050).map(|id| (glam::vec2(12.5, -40.0), 0.5, id)));
+
+    let mut reference = fresh(3, &points);
+
+    // a tree already holding something else, as a system keeps it between steps
+    let (mut tree, _) = scattered(2);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+    pool.install(|| tree.par_rebuild(BOUNDARY, points.clone()));
+
+    assert_eq!(tree.len(), reference.len());
+
+    let mass_of = |id: &usize| 1.0 + (*id % 7) as f32;
+    tree.compute_mass_distribution(&mass_of);
+    reference.compute_mass_distribution(&mass_of);
+
+    // the same tree answers in the same order, not just with the same points
+    for (pos, radius, _) in points.iter().step_by(97) {
+        assert_eq!(
+            tree.query(*pos, *radius * 4.0),
+            reference.query(*pos, *radius * 4.0)
+        );
+        assert_eq!(tree.k_nearest(*pos, 5), reference.k_nearest(*pos, 5));
+        assert_eq!(
+            tree.barnes_hut_acceleration(*pos, 0.5, 1.0, &mass_of),
+            reference.barnes_hut_acceleration(*pos, 0.5, 1.0, &mass_of)
+        );
+    }
+}
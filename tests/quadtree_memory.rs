@@ -0,0 +1,82 @@
+//! Counts every allocation of the process, so it's kept to a single test in a
+//! binary of its own
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use particle_simulator::quadtree::{QuadTree, Rect};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const BOUNDARY: Rect = Rect {
+    left: -100.0,
+    top: -100.0,
+    width: 200.0,
+    height: 200.0,
+};
+
+#[test]
+fn repeated_parallel_rebuilds_reuse_their_memory() {
+    let mut rng = ChaCha8Rng::seed_from_u64(12);
+
+    // enough to be split between threads a few levels down
+    let points = (0..20_000)
+        .map(|id| {
+            let pos = glam::vec2(
+                rng.random_range(-100.0..100.0),
+                rng.random_range(-100.0..100.0),
+            );
+            (pos, rng.random_range(0.0..2.0), id)
+        })
+        .collect::<Vec<_>>();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+
+    let mut tree = QuadTree::new(3, BOUNDARY);
+
+    // the first rebuilds allocate everything the tree and its scratch trees need
+    for _ in 0..3 {
+        pool.install(|| tree.par_rebuild(BOUNDARY, points.clone()));
+    }
+
+    for rebuild in 0..10 {
+        let points = points.clone();
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+
+        pool.install(|| tree.par_rebuild(BOUNDARY, points));
+
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        assert_eq!(
+            allocations, 0,
+            "rebuild {rebuild} allocated {allocations} times"
+        );
+    }
+
+    assert_eq!(tree.len(), points.len());
+}
@@ -0,0 +1,57 @@
+use particle_simulator::Scene;
+
+#[test]
+fn errors_report_the_line_of_the_bad_value() {
+    let source = "\
+size = [200, 200]
+
+[[particles]]
+position = [50, 100]
+radius = 10
+
+[[particles]]
+position = [100, 100]
+radius = -3
+";
+
+    let errors = Scene::parse(source).unwrap_err();
+    assert_eq!(errors.to_string(), "line 9: `radius` must be positive");
+
+    let source = "\
+size = [200, 200]
+
+[[emitters]]
+position = [100, 100]
+rate = 10
+speed = 50
+radius = 4
+lifetime = 0
+";
+
+    let errors = Scene::parse(source).unwrap_err();
+    assert_eq!(errors.to_string(), "line 8: `lifetime` must be positive");
+
+    let errors = Scene::parse("\n\nsize = [0, 200]\n").unwrap_err();
+    assert_eq!(errors.to_string(), "line 3: `size` must be positive");
+}
+
+#[test]
+fn shipped_scenes_are_valid() {
+    let mut paths = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let path = path.to_str().unwrap();
+
+        // parsing validates too
+        let scene = Scene::from_file(path).unwrap_or_else(|e| panic!("{path}:\n{e}"));
+        let mut simulation = scene.into_simulation();
+        simulation.step(1.0 / 120.0);
+    }
+}
@@ -0,0 +1,321 @@
+use legion::IntoQuery;
+use particle_simulator::boundary::{Boundary, Domain};
+use particle_simulator::broad_phase::BroadPhaseKind;
+use particle_simulator::colliders::{Collider, ColliderShape};
+use particle_simulator::components::{EntityIndex, Velocity};
+use particle_simulator::emitter::{Distribution, EmissionMode, Emitter, SpawnShape, ValueRange};
+use particle_simulator::forces::{ForceField, ForceFields};
+use particle_simulator::material::{Material, MaterialId};
+use particle_simulator::{Integrator, Simulation, Snapshot};
+
+/// A fountain of short lived, randomly sized particles falling onto a circle,
+/// with a burst emitter so both emission modes carry state between steps
+fn fountain(seed: u64) -> Simulation {
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), seed);
+    simulation.resources.insert(Integrator::Rk4);
+    simulation.resources.insert(ForceFields(vec![
+        ForceField::Gravity(glam::vec2(0.0, 300.0)),
+        ForceField::LinearDrag(0.1),
+    ]));
+
+    simulation.add_collider(Collider::new(ColliderShape::Circle {
+        centre: glam::vec2(200.0, 250.0),
+        radius: 40.0,
+    }));
+
+    let mut emitter = Emitter::new(glam::vec2(200.0, 100.0));
+    emitter.mode = EmissionMode::Continuous(37.5);
+    emitter.shape = SpawnShape::Line {
+        offset: glam::vec2(50.0, 0.0),
+    };
+    emitter.radius = Distribution::Normal {
+        mean: 4.0,
+        std_dev: 1.0,
+        range: ValueRange::new(2.0, 6.0),
+    };
+    emitter.lifetime = Some(1.5);
+    emitter.fade_color = [1.0, 0.5, 0.0];
+    simulation.add_emitter(emitter);
+
+    let mut burst = Emitter::new(glam::vec2(100.0, 100.0));
+    burst.mode = EmissionMode::Burst {
+        count: 5,
+        interval: 0.7,
+    };
+    burst.mass = Some(ValueRange::new(1.0, 2.0));
+    simulation.add_emitter(burst);
+
+    simulation
+}
+
+fn steps(simulation: &mut Simulation, count: usize) {
+    for _ in 0..count {
+        simulation.step(1.0 / 120.0);
+    }
+}
+
+/// Bits of every instance float followed by every velocity in instance order
+fn state(simulation: &Simulation) -> Vec<u32> {
+    let mut velocities = <(&EntityIndex, &Velocity)>::query()
+        .iter(&simulation.world)
+        .map(|(EntityIndex(index), Velocity(vel))| (*index, *vel))
+        .collect::<Vec<_>>();
+    velocities.sort_by_key(|(index, _)| *index);
+
+    simulation
+        .instances()
+        .as_slice()
+        .iter()
+        .copied()
+        .chain(velocities.into_iter().flat_map(|(_, vel)| vel.to_array()))
+        .map(f32::to_bits)
+        .collect()
+}
+
+#[test]
+fn binary_round_trip_is_lossless() {
+    let mut simulation = fountain(3);
+    steps(&mut simulation, 200);
+
+    let snapshot = Snapshot::capture(&simulation);
+    assert!(!snapshot.particles.is_empty());
+
+    let mut bytes = vec![];
+    snapshot.write_binary(&mut bytes).unwrap();
+
+    assert_eq!(Snapshot::read_binary(bytes.as_slice()).unwrap(), snapshot);
+}
+
+#[test]
+fn ron_round_trip_is_lossless() {
+    let mut simulation = fountain(4);
+    steps(&mut simulation, 200);
+
+    let snapshot = Snapshot::capture(&simulation);
+    let path = std::env::temp_dir().join(format!("snapshot-{}.ron", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    snapshot.save(path).unwrap();
+    let loaded = Snapshot::load(path);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(loaded.unwrap(), snapshot);
+}
+
+#[test]
+fn restored_simulation_continues_bit_identically() {
+    let mut simulation = fountain(5);
+    steps(&mut simulation, 150);
+
+    let mut bytes = vec![];
+    Snapshot::capture(&simulation)
+        .write_binary(&mut bytes)
+        .unwrap();
+    let mut restored = Snapshot::read_binary(bytes.as_slice()).unwrap().restore();
+
+    assert_eq!(state(&restored), state(&simulation));
+
+    steps(&mut simulation, 150);
+    steps(&mut restored, 150);
+
+    assert_eq!(state(&restored), state(&simulation));
+}
+
+/// Writes and reads back `snapshot` in both formats
+fn assert_round_trips(snapshot: &Snapshot) {
+    let mut bytes = vec![];
+    snapshot.write_binary(&mut bytes).unwrap();
+    assert_eq!(&Snapshot::read_binary(bytes.as_slice()).unwrap(), snapshot);
+
+    let ron = ron::to_string(snapshot).unwrap();
+    assert_eq!(&ron::from_str::<Snapshot>(&ron).unwrap(), snapshot);
+}
+
+#[test]
+fn every_variant_round_trips() {
+    let mut simulation = fountain(9);
+    steps(&mut simulation, 60);
+
+    let mut snapshot = Snapshot::capture(&simulation);
+    assert!(!snapshot.particles.is_empty());
+
+    snapshot.domain = Domain {
+        left: Boundary::Reflect { restitution: 0.5 },
+        right: Boundary::Open,
+        top: Boundary::Wrap,
+        bottom: Boundary::Absorb,
+        ..snapshot.domain
+    };
+
+    snapshot.force_fields = vec![
+        ForceField::Gravity(glam::vec2(0.0, 9.8)),
+        ForceField::LinearDrag(0.1),
+        ForceField::QuadraticDrag(0.01),
+        ForceField::Wind {
+            velocity: glam::vec2(-20.0, 0.0),
+            drag: 0.3,
+        },
+    ];
+
+    snapshot.colliders = [
+        ColliderShape::Segment {
+            a: glam::vec2(0.0, 300.0),
+            b: glam::vec2(400.0, 350.0),
+        },
+        ColliderShape::Aabb {
+            min: glam::vec2(10.0, 10.0),
+            max: glam::vec2(30.0, 20.0),
+        },
+        ColliderShape::Circle {
+            centre: glam::vec2(200.0, 200.0),
+            radius: 25.0,
+        },
+        ColliderShape::Polygon(vec![
+            glam::vec2(300.0, 300.0),
+            glam::vec2(350.0, 300.0),
+            glam::vec2(325.0, 260.0),
+        ]),
+    ]
+    .into_iter()
+    .map(Collider::new)
+    .collect();
+
+    let template = snapshot.emitters[0];
+    let range = ValueRange::new(1.0, 4.0);
+
+    let distributions = [
+        Distribution::Uniform(range),
+        Distribution::Normal {
+            mean: 2.0,
+            std_dev: 0.5,
+            range,
+        },
+        Distribution::LogNormal {
+            median: 2.0,
+            sigma: 0.3,
+        },
+        Distribution::PowerLaw {
+            range,
+            exponent: 2.5,
+        },
+    ];
+
+    let shapes = [
+        SpawnShape::Point,
+        SpawnShape::Circle { radius: 10.0 },
+        SpawnShape::Line {
+            offset: glam::vec2(5.0, 5.0),
+        },
+        SpawnShape::Rectangle {
+            size: glam::vec2(20.0, 10.0),
+        },
+    ];
+
+    let modes = [
+        EmissionMode::Continuous(12.5),
+        EmissionMode::Burst {
+            count: 3,
+            interval: f32::INFINITY,
+        },
+    ];
+
+    snapshot.emitters = distributions
+        .into_iter()
+        .zip(shapes)
+        .zip(modes.into_iter().cycle())
+        .map(|((radius, shape), mode)| {
+            let mut emitter = template;
+            emitter.radius = radius;
+            emitter.shape = shape;
+            emitter.mode = mode;
+            emitter.mass = Some(range);
+            emitter.color = None;
+            emitter.lifetime = None;
+            emitter
+        })
+        .collect();
+
+    snapshot.materials.insert("sand", Material::default());
+    snapshot.particles[0].material = MaterialId(1);
+    snapshot.particles[0].lifetime = None;
+    snapshot.particles[0].fade = None;
+
+    for integrator in [
+        Integrator::ExplicitEuler,
+        Integrator::SemiImplicitEuler,
+        Integrator::VelocityVerlet,
+        Integrator::Rk4,
+    ] {
+        for broad_phase in [BroadPhaseKind::QuadTree, BroadPhaseKind::Grid] {
+            snapshot.integrator = integrator;
+            snapshot.broad_phase = broad_phase;
+
+            assert_round_trips(&snapshot);
+        }
+    }
+}
+
+#[test]
+fn corrupt_snapshots_are_rejected() {
+    let mut simulation = fountain(6);
+    steps(&mut simulation, 50);
+
+    let mut bytes = vec![];
+    Snapshot::capture(&simulation)
+        .write_binary(&mut bytes)
+        .unwrap();
+
+    let mut wrong_version = bytes.clone();
+    wrong_version[4] ^= 0xff;
+    assert!(Snapshot::read_binary(wrong_version.as_slice()).is_err());
+
+    let truncated = &bytes[..bytes.len() - 1];
+    assert!(Snapshot::read_binary(truncated).is_err());
+}
+
+#[test]
+fn ron_snapshot_with_an_empty_palette_is_rejected() {
+    let mut snapshot = Snapshot::capture(&fountain(7));
+    snapshot.materials.insert(
+        "default",
+        Material {
+            palette: vec![],
+            ..Default::default()
+        },
+    );
+
+    let path = std::env::temp_dir().join(format!("empty-palette-{}.ron", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    snapshot.save(path).unwrap();
+    let loaded = Snapshot::load(path);
+    std::fs::remove_file(path).unwrap();
+
+    assert!(loaded.unwrap_err().to_string().contains("without colors"));
+}
+
+#[test]
+fn snapshots_with_impossible_settings_are_rejected() {
+    let snapshot = Snapshot::capture(&fountain(8));
+
+    let mut empty = snapshot.clone();
+    empty.domain.size.x = 0.0;
+
+    let mut no_capacity = snapshot.clone();
+    no_capacity.quad_capacity = 0;
+
+    let mut no_passes = snapshot;
+    no_passes.contact_passes = 0;
+
+    for (snapshot, message) in [
+        (empty, "`size` must be positive"),
+        (no_capacity, "`quad_capacity` must be at least 1"),
+        (no_passes, "`contact_passes` must be at least 1"),
+    ] {
+        let mut bytes = vec![];
+        snapshot.write_binary(&mut bytes).unwrap();
+
+        let error = Snapshot::read_binary(bytes.as_slice()).unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+}
@@ -0,0 +1,204 @@
+use glam::Vec2;
+use legion::{Entity, EntityStore, IntoQuery};
+use particle_simulator::boundary::{Boundary, Domain};
+use particle_simulator::colliders::{Collider, ColliderShape};
+use particle_simulator::components::{Mass, Surface, Velocity};
+use particle_simulator::material::Material;
+use particle_simulator::Simulation;
+
+const RESTITUTION: f32 = 0.6;
+
+/// Spawns a white particle of a material with `surface`
+fn spawn(simulation: &mut Simulation, pos: Vec2, vel: Vec2, mass: f32, surface: Surface) -> Entity {
+    let material = simulation.add_material(
+        &format!("{surface:?}"),
+        Material {
+            surface,
+            ..Default::default()
+        },
+    );
+
+    let entity = simulation.spawn([pos.x, pos.y, 5.0, 1.0, 1.0, 1.0], vel, mass);
+    simulation
+        .world
+        .entry(entity)
+        .unwrap()
+        .add_component(material);
+
+    entity
+}
+
+fn velocity(simulation: &Simulation, entity: Entity) -> Vec2 {
+    simulation
+        .world
+        .entry_ref(entity)
+        .unwrap()
+        .get_component::<Velocity>()
+        .unwrap()
+        .0
+}
+
+fn kinetic_energy(simulation: &Simulation) -> f32 {
+    <(&Velocity, &Mass)>::query()
+        .iter(&simulation.world)
+        .map(|(Velocity(vel), Mass(mass))| 0.5 * mass * vel.length_squared())
+        .sum()
+}
+
+fn steps(simulation: &mut Simulation, count: usize) {
+    for _ in 0..count {
+        simulation.step(1.0 / 120.0);
+    }
+}
+
+fn bouncy(restitution: f32, friction: f32) -> Surface {
+    Surface {
+        restitution,
+        friction,
+    }
+}
+
+#[test]
+fn head_on_pair_keeps_restitution_of_normal_speed() {
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), 0);
+    let surface = bouncy(RESTITUTION, 0.0);
+
+    let (m1, m2) = (1.0, 3.0);
+    let (v1, v2) = (glam::vec2(100.0, 0.0), glam::vec2(-60.0, 0.0));
+    let a = spawn(&mut simulation, glam::vec2(170.0, 200.0), v1, m1, surface);
+    let b = spawn(&mut simulation, glam::vec2(230.0, 200.0), v2, m2, surface);
+
+    let energy = kinetic_energy(&simulation);
+    steps(&mut simulation, 60);
+
+    let (after_a, after_b) = (velocity(&simulation, a), velocity(&simulation, b));
+    let approach = (v1 - v2).x;
+    let separation = (after_b - after_a).x;
+
+    assert!((separation - RESTITUTION * approach).abs() < approach * 1e-3);
+
+    // momentum is kept and the energy in the centre of mass frame drops by 1 - e^2
+    let momentum = m1 * v1 + m2 * v2;
+    assert!((m1 * after_a + m2 * after_b - momentum).length() < 1e-2);
+
+    let reduced_mass = m1 * m2 / (m1 + m2);
+    let expected_loss =
+        (1.0 - RESTITUTION * RESTITUTION) * 0.5 * reduced_mass * approach * approach;
+    let loss = energy - kinetic_energy(&simulation);
+    assert!(
+        (loss - expected_loss).abs() < expected_loss * 1e-3,
+        "lost {loss}, expected {expected_loss}"
+    );
+}
+
+#[test]
+fn wall_bounce_keeps_the_smaller_restitution() {
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), 0);
+    simulation.set_domain(Domain::with_boundary(
+        glam::vec2(400.0, 400.0),
+        Boundary::Reflect { restitution: 0.9 },
+    ));
+
+    let vel = glam::vec2(120.0, 0.0);
+    let particle = spawn(
+        &mut simulation,
+        glam::vec2(380.0, 200.0),
+        vel,
+        1.0,
+        bouncy(RESTITUTION, 0.0),
+    );
+
+    steps(&mut simulation, 30);
+
+    let after = velocity(&simulation, particle);
+    assert!((after.x + RESTITUTION * vel.x).abs() < 1e-3, "{after}");
+    assert_eq!(after.y, 0.0);
+}
+
+#[test]
+fn collider_bounce_keeps_the_smaller_restitution() {
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), 0);
+    simulation.add_collider(Collider {
+        shape: ColliderShape::Aabb {
+            min: glam::vec2(100.0, 300.0),
+            max: glam::vec2(300.0, 350.0),
+        },
+        surface: bouncy(RESTITUTION, 0.0),
+    });
+
+    let vel = glam::vec2(0.0, 150.0);
+    let particle = spawn(
+        &mut simulation,
+        glam::vec2(200.0, 280.0),
+        vel,
+        1.0,
+        bouncy(0.95, 0.0),
+    );
+
+    let energy = kinetic_energy(&simulation);
+    steps(&mut simulation, 30);
+
+    let after = velocity(&simulation, particle);
+    assert!((after.y + RESTITUTION * vel.y).abs() < 1e-3, "{after}");
+
+    let kept = kinetic_energy(&simulation) / energy;
+    assert!((kept - RESTITUTION * RESTITUTION).abs() < 1e-4);
+}
+
+#[test]
+fn friction_slows_sliding_by_its_share_of_the_normal_impulse() {
+    let friction = 0.2;
+
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), 0);
+    simulation.add_collider(Collider {
+        shape: ColliderShape::Aabb {
+            min: glam::vec2(0.0, 300.0),
+            max: glam::vec2(400.0, 350.0),
+        },
+        surface: bouncy(RESTITUTION, friction),
+    });
+
+    // hits the floor at 45 degrees
+    let vel = glam::vec2(100.0, 100.0);
+    let particle = spawn(
+        &mut simulation,
+        glam::vec2(100.0, 280.0),
+        vel,
+        1.0,
+        Surface::default(),
+    );
+
+    steps(&mut simulation, 30);
+
+    // the normal impulse per unit mass is (1 + e) * vn, friction takes its share
+    // of that from the tangential speed
+    let after = velocity(&simulation, particle);
+    let expected_tangential = vel.x - friction * (1.0 + RESTITUTION) * vel.y;
+
+    assert!((after.x - expected_tangential).abs() < 1e-3, "{after}");
+    assert!((after.y + RESTITUTION * vel.y).abs() < 1e-3, "{after}");
+}
+
+#[test]
+fn friction_stops_sliding_without_reversing_it() {
+    let mut simulation = Simulation::new(glam::vec2(400.0, 400.0), 0);
+    simulation.add_collider(Collider {
+        shape: ColliderShape::Aabb {
+            min: glam::vec2(0.0, 300.0),
+            max: glam::vec2(400.0, 350.0),
+        },
+        surface: bouncy(RESTITUTION, 5.0),
+    });
+
+    let particle = spawn(
+        &mut simulation,
+        glam::vec2(100.0, 280.0),
+        glam::vec2(100.0, 100.0),
+        1.0,
+        Surface::default(),
+    );
+
+    steps(&mut simulation, 30);
+
+    assert_eq!(velocity(&simulation, particle).x, 0.0);
+}
@@ -0,0 +1,158 @@
+use glam::Vec2;
+use particle_simulator::boundary::{Boundary, Domain};
+use particle_simulator::{FixedTimestep, Simulation, FLOATS_PER_INSTANCE};
+
+// a power of two so frame times add up without rounding
+const STEP: f32 = 1.0 / 128.0;
+
+fn simulation() -> Simulation {
+    let size = glam::vec2(400.0, 300.0);
+
+    let mut simulation = Simulation::new(size, 0);
+    simulation.set_domain(Domain::with_boundary(size, Boundary::Wrap));
+    simulation.timestep = FixedTimestep::new(STEP, 8);
+    simulation
+}
+
+fn spawn(simulation: &mut Simulation, x: f32, vel_x: f32) {
+    simulation.spawn([x, 150.0, 5.0, 1.0, 1.0, 1.0], glam::vec2(vel_x, 0.0), 1.0);
+}
+
+/// Positions written by `write_interpolated`, in instance order
+fn interpolated(simulation: &Simulation, alpha: f32) -> Vec<Vec2> {
+    let mut out = vec![0.0; simulation.len() * FLOATS_PER_INSTANCE];
+    simulation.write_interpolated(alpha, &mut out);
+
+    out.chunks_exact(FLOATS_PER_INSTANCE)
+        .map(Vec2::from_slice)
+        .collect()
+}
+
+#[test]
+fn frame_times_add_up_to_the_same_steps() {
+    let splits: [&[f32]; 4] = [
+        &[0.5; 2],
+        &[1.0 / 32.0; 32],
+        &[3.0 / 128.0, 5.0 / 128.0, 1.0 / 256.0, 7.0 / 256.0, 0.9375],
+        &[0.001; 1000],
+    ];
+
+    for frames in splits {
+        // enough substeps that none of the frames is capped
+        let mut timestep = FixedTimestep::new(STEP, 1000);
+        let steps: usize = frames.iter().map(|dt| timestep.advance(*dt)).sum();
+
+        let total: f32 = frames.iter().sum();
+        let expected = (total / STEP) as usize;
+
+        assert!(steps.abs_diff(expected) <= 1, "{steps} steps for {total}s");
+        let simulated = (steps as f32 + timestep.alpha()) * STEP;
+        assert!(
+            (simulated - total).abs() < 1e-4,
+            "{simulated}s simulated for {total}s"
+        );
+    }
+}
+
+#[test]
+fn simulation_advance_doesnt_depend_on_the_frame_times() {
+    let run = |frames: &[f32]| {
+        let mut simulation = simulation();
+        simulation.timestep = FixedTimestep::new(STEP, 64);
+        spawn(&mut simulation, 100.0, 37.0);
+        spawn(&mut simulation, 200.0, -53.0);
+
+        for dt in frames {
+            simulation.advance(*dt);
+        }
+
+        let instances = simulation.instances();
+        instances.as_slice().to_vec()
+    };
+
+    let reference = run(&[0.25; 4]);
+    assert_eq!(run(&[1.0 / 16.0; 16]), reference);
+    assert_eq!(run(&[3.0 / 128.0, 61.0 / 128.0, 0.5]), reference);
+}
+
+#[test]
+fn substeps_are_capped_and_the_rest_is_dropped() {
+    let mut timestep = FixedTimestep::new(STEP, 4);
+
+    // a one second stall, plus half a step
+    assert_eq!(timestep.advance(1.0 + STEP / 2.0), 4);
+    assert_eq!(timestep.alpha(), 0.5);
+
+    // only the half step was carried over
+    assert_eq!(timestep.advance(STEP / 2.0), 1);
+    assert_eq!(timestep.alpha(), 0.0);
+    assert_eq!(timestep.advance(0.0), 0);
+}
+
+#[test]
+fn alpha_stays_below_one() {
+    let mut timestep = FixedTimestep::new(1.0 / 120.0, 8);
+
+    // frame times of a jittery display, none of them multiples of the step
+    for frame in 0..10_000 {
+        let dt = 1.0 / 60.0 + (frame % 7) as f32 * 1e-3 - 3e-3;
+        timestep.advance(dt);
+
+        let alpha = timestep.alpha();
+        assert!(
+            (0.0..1.0).contains(&alpha),
+            "alpha of {alpha} on frame {frame}"
+        );
+    }
+}
+
+#[test]
+fn interpolation_skips_wrapped_particles() {
+    let mut simulation = simulation();
+
+    // two units per step, the first one crosses the right edge
+    spawn(&mut simulation, 399.0, 256.0);
+    spawn(&mut simulation, 100.0, 256.0);
+
+    let alpha = simulation.advance(STEP * 1.5);
+    assert_eq!(alpha, 0.5);
+
+    let positions = interpolated(&simulation, alpha);
+
+    let instances = simulation.instances();
+    let wrapped = Vec2::from_slice(instances.get(0));
+    assert!(wrapped.x < 5.0, "{wrapped}");
+
+    // drawn where it is rather than halfway across the domain
+    assert_eq!(positions[0], wrapped);
+    assert!((positions[1] - glam::vec2(101.0, 150.0)).length() < 1e-3);
+}
+
+#[test]
+fn interpolation_stays_aligned_after_despawning() {
+    let mut simulation = simulation();
+
+    let first = simulation.spawn(
+        [50.0, 150.0, 5.0, 1.0, 1.0, 1.0],
+        glam::vec2(256.0, 0.0),
+        1.0,
+    );
+    spawn(&mut simulation, 150.0, 256.0);
+    spawn(&mut simulation, 250.0, -256.0);
+
+    let alpha = simulation.advance(STEP * 1.5);
+    assert!(simulation.despawn(first));
+
+    // the last particle took the freed slot along with its previous position
+    let positions = interpolated(&simulation, alpha);
+    assert_eq!(positions.len(), 2);
+    assert!((positions[0] - glam::vec2(249.0, 150.0)).length() < 1e-3);
+    assert!((positions[1] - glam::vec2(151.0, 150.0)).length() < 1e-3);
+
+    // particles spawned since the last step are drawn where they are
+    spawn(&mut simulation, 300.0, 256.0);
+    assert_eq!(
+        interpolated(&simulation, alpha)[2],
+        glam::vec2(300.0, 150.0)
+    );
+}